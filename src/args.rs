@@ -1,32 +1,253 @@
+use crate::crawling::LinkKind;
 use crate::error::CrawlerError::ParsingError;
+use crate::priority::Priority;
+use crate::scope::Scope;
 use crate::shared;
+use std::str::FromStr;
+use std::time::Duration;
+
+static USAGE: &str =
+    "Usage: crawler (URL OUT_DIR | --resume STATE) NUM_THREADS [--max-depth DEPTH] \
+[--max-pages PAGES] [--max-bytes BYTES] [--max-duration SECONDS] \
+[--scope any|host|domain|prefix|hosts:HOST,...] [--ignore-robots] [--ignore-nofollow] \
+[--delay MILLISECONDS] [--max-per-host CONNECTIONS] [--follow a,area,link,img,iframe,form,refresh] \
+[--tracking-params PARAM,...] [--collect-contacts] [--queue-buffer JOBS] \
+[--checkpoint-interval SECONDS] [--priority bfs|dfs|shallowest|round-robin|keywords:WORD,...] \
+[--filter FILE] [--max-redirects HOPS] [--user-agent USER_AGENT] [--connect-timeout SECONDS] \
+[--read-timeout SECONDS] [--timeout SECONDS] [--header \"NAME: VALUE\"]... [--proxy URL] \
+[--insecure] [--max-retries RETRIES] [--retry-delay MILLISECONDS] [--max-size BYTES] \
+[--max-size-for CONTENT_TYPE:BYTES]... [--truncate] [--history FILE]";
+// the time between two checkpoints if nothing else is configured
+static CHECKPOINT_INTERVAL: u64 = 60;
 
 #[derive(PartialEq, Debug)]
 pub struct Args<'a> {
+    // both are empty when resuming since they're taken from the state file
     pub url: &'a str,
     pub out_dir: &'a str,
     pub num_threads: usize,
+    pub max_depth: Option<usize>,
+    pub max_pages: Option<usize>,
+    pub max_bytes: Option<usize>,
+    pub max_duration: Option<Duration>,
+    pub scope: Scope,
+    pub ignore_robots: bool,
+    pub ignore_nofollow: bool,
+    pub delay: Duration,
+    pub max_per_host: Option<usize>,
+    pub link_kinds: Vec<LinkKind>,
+    pub tracking_params: Option<Vec<String>>,
+    pub collect_contacts: bool,
+    pub queue_buffer: Option<usize>,
+    pub resume: Option<&'a str>,
+    pub checkpoint_interval: Duration,
+    pub priority: Priority,
+    pub filter: Option<&'a str>,
+    pub max_redirects: Option<usize>,
+    pub user_agent: Option<&'a str>,
+    pub connect_timeout: Option<Duration>,
+    pub read_timeout: Option<Duration>,
+    pub timeout: Option<Duration>,
+    pub headers: Vec<(&'a str, &'a str)>,
+    pub proxy: Option<&'a str>,
+    pub insecure: bool,
+    pub max_retries: Option<usize>,
+    // the base delay which doubles with every retry
+    pub retry_delay: Option<Duration>,
+    pub max_size: Option<usize>,
+    // limits for content types which contain the given value, e.g. `html`
+    pub max_sizes_for: Vec<(&'a str, usize)>,
+    pub truncate: bool,
+    // pages which are listed in the history from a previous crawl are only crawled if they changed
+    pub history: Option<&'a str>,
 }
 
 impl<'a> Args<'a> {
     pub fn new(args: &'a [String]) -> shared::Result<Self> {
-        if args.len() == 4 {
+        let mut positional: Vec<&'a str> = vec![];
+        let mut max_depth = None;
+        let mut max_pages = None;
+        let mut max_bytes = None;
+        let mut max_duration = None;
+        let mut scope = Scope::default();
+        let mut ignore_robots = false;
+        let mut ignore_nofollow = false;
+        let mut delay = Duration::from_secs(0);
+        let mut max_per_host = None;
+        let mut link_kinds = LinkKind::defaults();
+        let mut tracking_params = None;
+        let mut collect_contacts = false;
+        let mut queue_buffer = None;
+        let mut resume = None;
+        let mut checkpoint_interval = Duration::from_secs(CHECKPOINT_INTERVAL);
+        let mut priority = Priority::default();
+        let mut filter = None;
+        let mut max_redirects = None;
+        let mut user_agent = None;
+        let mut connect_timeout = None;
+        let mut read_timeout = None;
+        let mut timeout = None;
+        let mut headers = vec![];
+        let mut proxy = None;
+        let mut insecure = false;
+        let mut max_retries = None;
+        let mut retry_delay = None;
+        let mut max_size = None;
+        let mut max_sizes_for = vec![];
+        let mut truncate = false;
+        let mut history = None;
+
+        let mut iter = args.iter().skip(1);
+        while let Some(arg) = iter.next() {
+            match arg.as_str() {
+                "--max-depth" => max_depth = Some(parse_option(arg, iter.next())?),
+                "--max-pages" => max_pages = Some(parse_option(arg, iter.next())?),
+                "--max-bytes" => max_bytes = Some(parse_option(arg, iter.next())?),
+                "--max-duration" => {
+                    max_duration = Some(Duration::from_secs(parse_option(arg, iter.next())?))
+                }
+                "--scope" => scope = parse_option(arg, iter.next())?,
+                "--ignore-robots" => ignore_robots = true,
+                "--ignore-nofollow" => ignore_nofollow = true,
+                "--delay" => delay = Duration::from_millis(parse_option(arg, iter.next())?),
+                "--max-per-host" => max_per_host = Some(parse_option(arg, iter.next())?),
+                "--follow" => link_kinds = parse_list(arg, iter.next())?,
+                "--tracking-params" => tracking_params = Some(parse_list(arg, iter.next())?),
+                "--collect-contacts" => collect_contacts = true,
+                "--queue-buffer" => queue_buffer = Some(parse_option(arg, iter.next())?),
+                "--resume" => resume = Some(parse_str(arg, iter.next())?),
+                "--checkpoint-interval" => {
+                    checkpoint_interval = Duration::from_secs(parse_option(arg, iter.next())?)
+                }
+                "--priority" => priority = parse_option(arg, iter.next())?,
+                "--filter" => filter = Some(parse_str(arg, iter.next())?),
+                "--max-redirects" => max_redirects = Some(parse_option(arg, iter.next())?),
+                "--user-agent" => user_agent = Some(parse_str(arg, iter.next())?),
+                "--connect-timeout" => {
+                    connect_timeout = Some(Duration::from_secs(parse_option(arg, iter.next())?))
+                }
+                "--read-timeout" => {
+                    read_timeout = Some(Duration::from_secs(parse_option(arg, iter.next())?))
+                }
+                "--timeout" => timeout = Some(Duration::from_secs(parse_option(arg, iter.next())?)),
+                "--header" => headers.push(parse_header(arg, iter.next())?),
+                "--proxy" => proxy = Some(parse_str(arg, iter.next())?),
+                "--insecure" => insecure = true,
+                "--max-retries" => max_retries = Some(parse_option(arg, iter.next())?),
+                "--retry-delay" => {
+                    retry_delay = Some(Duration::from_millis(parse_option(arg, iter.next())?))
+                }
+                "--max-size" => max_size = Some(parse_option(arg, iter.next())?),
+                "--max-size-for" => max_sizes_for.push(parse_size_for(arg, iter.next())?),
+                "--truncate" => truncate = true,
+                "--history" => history = Some(parse_str(arg, iter.next())?),
+                _ if arg.starts_with("--") => return Err(ParsingError(USAGE.to_string())),
+                _ => positional.push(arg.as_str()),
+            }
+        }
+
+        let positional = match (resume, positional.as_slice()) {
+            (None, [url, out_dir, num_threads]) => Some((*url, *out_dir, *num_threads)),
+            (Some(_), [num_threads]) => Some(("", "", *num_threads)),
+            _ => None,
+        };
+        if let Some((url, out_dir, num_threads)) = positional {
             let args = Args {
-                url: args[1].as_str(),
-                out_dir: args[2].as_str(),
-                num_threads: args[3].parse::<usize>().unwrap(),
+                url,
+                out_dir,
+                num_threads: num_threads.parse::<usize>().unwrap(),
+                max_depth,
+                max_pages,
+                max_bytes,
+                max_duration,
+                scope,
+                ignore_robots,
+                ignore_nofollow,
+                delay,
+                max_per_host,
+                link_kinds,
+                tracking_params,
+                collect_contacts,
+                queue_buffer,
+                resume,
+                checkpoint_interval,
+                priority,
+                filter,
+                max_redirects,
+                user_agent,
+                connect_timeout,
+                read_timeout,
+                timeout,
+                headers,
+                proxy,
+                insecure,
+                max_retries,
+                retry_delay,
+                max_size,
+                max_sizes_for,
+                truncate,
+                history,
             };
             return Ok(args);
         }
-        Err(ParsingError(
-            "Usage: crawler URL OUT_DIR NUM_THREADS".to_string(),
-        ))
+        Err(ParsingError(USAGE.to_string()))
     }
 }
 
+fn parse_option<T>(name: &str, value: Option<&String>) -> shared::Result<T>
+where
+    T: FromStr,
+{
+    value
+        .and_then(|value| value.parse::<T>().ok())
+        .ok_or_else(|| ParsingError(format!("Invalid value for \"{}\"\n{}", name, USAGE)))
+}
+
+fn parse_str<'a>(name: &str, value: Option<&'a String>) -> shared::Result<&'a str> {
+    value
+        .map(|value| value.as_str())
+        .ok_or_else(|| ParsingError(format!("Invalid value for \"{}\"\n{}", name, USAGE)))
+}
+
+// e.g. `Accept-Language: en`
+fn parse_header<'a>(name: &str, value: Option<&'a String>) -> shared::Result<(&'a str, &'a str)> {
+    value
+        .and_then(|value| value.split_once(':'))
+        .map(|(header, value)| (header.trim(), value.trim()))
+        .filter(|(header, _)| !header.is_empty())
+        .ok_or_else(|| ParsingError(format!("Invalid value for \"{}\"\n{}", name, USAGE)))
+}
+
+// e.g. `application/pdf:100000000`
+fn parse_size_for<'a>(name: &str, value: Option<&'a String>) -> shared::Result<(&'a str, usize)> {
+    value
+        .and_then(|value| value.rsplit_once(':'))
+        .and_then(|(kind, size)| Some((kind.trim(), size.trim().parse::<usize>().ok()?)))
+        .filter(|(kind, _)| !kind.is_empty())
+        .ok_or_else(|| ParsingError(format!("Invalid value for \"{}\"\n{}", name, USAGE)))
+}
+
+fn parse_list<T>(name: &str, value: Option<&String>) -> shared::Result<Vec<T>>
+where
+    T: FromStr,
+{
+    value
+        .and_then(|value| {
+            value
+                .split(',')
+                .map(|item| item.trim().parse::<T>().ok())
+                .collect::<Option<Vec<T>>>()
+        })
+        .ok_or_else(|| ParsingError(format!("Invalid value for \"{}\"\n{}", name, USAGE)))
+}
+
 #[cfg(test)]
 mod tests {
     use crate::args::Args;
+    use crate::crawling::LinkKind;
+    use crate::priority::Priority;
+    use crate::scope::Scope;
+    use std::time::Duration;
 
     #[test]
     fn args_success() {
@@ -42,13 +263,386 @@ mod tests {
                 url: "http://example.com",
                 out_dir: "./crawlings",
                 num_threads: 6,
+                max_depth: None,
+                max_pages: None,
+                max_bytes: None,
+                max_duration: None,
+                scope: Scope::Any,
+                ignore_robots: false,
+                ignore_nofollow: false,
+                delay: Duration::from_secs(0),
+                max_per_host: None,
+                link_kinds: LinkKind::defaults(),
+                tracking_params: None,
+                collect_contacts: false,
+                queue_buffer: None,
+                resume: None,
+                checkpoint_interval: Duration::from_secs(60),
+                priority: Priority::BreadthFirst,
+                filter: None,
+                max_redirects: None,
+                user_agent: None,
+                connect_timeout: None,
+                read_timeout: None,
+                timeout: None,
+                headers: vec![],
+                proxy: None,
+                insecure: false,
+                max_retries: None,
+                retry_delay: None,
+                max_size: None,
+                max_sizes_for: vec![],
+                truncate: false,
+                history: None,
             }
         );
     }
 
+    #[test]
+    fn args_success_max_depth() {
+        let args = vec![
+            "file".to_string(),
+            "http://example.com".to_string(),
+            "./crawlings".to_string(),
+            "6".to_string(),
+            "--max-depth".to_string(),
+            "3".to_string(),
+        ];
+        assert_eq!(Args::new(&args).unwrap().max_depth, Some(3));
+    }
+
+    #[test]
+    fn args_success_limits() {
+        let args = vec![
+            "file".to_string(),
+            "--max-pages".to_string(),
+            "100".to_string(),
+            "http://example.com".to_string(),
+            "./crawlings".to_string(),
+            "6".to_string(),
+            "--max-bytes".to_string(),
+            "1024".to_string(),
+            "--max-duration".to_string(),
+            "60".to_string(),
+        ];
+        let args = Args::new(&args).unwrap();
+        assert_eq!(args.url, "http://example.com");
+        assert_eq!(args.max_pages, Some(100));
+        assert_eq!(args.max_bytes, Some(1024));
+        assert_eq!(args.max_duration, Some(Duration::from_secs(60)));
+    }
+
+    #[test]
+    fn args_success_scope() {
+        let args = vec![
+            "file".to_string(),
+            "http://example.com".to_string(),
+            "./crawlings".to_string(),
+            "6".to_string(),
+            "--scope".to_string(),
+            "hosts:example.com,jdoe.com".to_string(),
+        ];
+        assert_eq!(
+            Args::new(&args).unwrap().scope,
+            Scope::Hosts(vec!["example.com".to_string(), "jdoe.com".to_string()])
+        );
+    }
+
+    #[test]
+    fn args_success_ignore_robots() {
+        let args = vec![
+            "file".to_string(),
+            "http://example.com".to_string(),
+            "./crawlings".to_string(),
+            "6".to_string(),
+            "--ignore-robots".to_string(),
+            "--ignore-nofollow".to_string(),
+        ];
+        let args = Args::new(&args).unwrap();
+        assert!(args.ignore_robots);
+        assert!(args.ignore_nofollow);
+    }
+
+    #[test]
+    fn args_success_politeness() {
+        let args = vec![
+            "file".to_string(),
+            "http://example.com".to_string(),
+            "./crawlings".to_string(),
+            "6".to_string(),
+            "--delay".to_string(),
+            "500".to_string(),
+            "--max-per-host".to_string(),
+            "2".to_string(),
+        ];
+        let args = Args::new(&args).unwrap();
+        assert_eq!(args.delay, Duration::from_millis(500));
+        assert_eq!(args.max_per_host, Some(2));
+    }
+
+    #[test]
+    fn args_success_follow() {
+        let args = vec![
+            "file".to_string(),
+            "http://example.com".to_string(),
+            "./crawlings".to_string(),
+            "6".to_string(),
+            "--follow".to_string(),
+            "a,iframe,refresh".to_string(),
+        ];
+        assert_eq!(
+            Args::new(&args).unwrap().link_kinds,
+            vec![LinkKind::Anchor, LinkKind::Iframe, LinkKind::MetaRefresh]
+        );
+    }
+
+    #[test]
+    fn args_success_tracking_params() {
+        let args = vec![
+            "file".to_string(),
+            "http://example.com".to_string(),
+            "./crawlings".to_string(),
+            "6".to_string(),
+            "--tracking-params".to_string(),
+            "utm_*,ref".to_string(),
+        ];
+        assert_eq!(
+            Args::new(&args).unwrap().tracking_params,
+            Some(vec!["utm_*".to_string(), "ref".to_string()])
+        );
+    }
+
+    #[test]
+    fn args_success_collect_contacts() {
+        let args = vec![
+            "file".to_string(),
+            "http://example.com".to_string(),
+            "./crawlings".to_string(),
+            "6".to_string(),
+            "--collect-contacts".to_string(),
+        ];
+        assert!(Args::new(&args).unwrap().collect_contacts);
+    }
+
+    #[test]
+    fn args_success_queue_buffer() {
+        let args = vec![
+            "file".to_string(),
+            "http://example.com".to_string(),
+            "./crawlings".to_string(),
+            "6".to_string(),
+            "--queue-buffer".to_string(),
+            "1000".to_string(),
+        ];
+        assert_eq!(Args::new(&args).unwrap().queue_buffer, Some(1000));
+    }
+
+    #[test]
+    fn args_success_resume() {
+        let args = vec![
+            "file".to_string(),
+            "--resume".to_string(),
+            "./crawlings/2020-01-01/crawler.state".to_string(),
+            "6".to_string(),
+            "--checkpoint-interval".to_string(),
+            "10".to_string(),
+        ];
+        let args = Args::new(&args).unwrap();
+        assert_eq!(args.resume, Some("./crawlings/2020-01-01/crawler.state"));
+        assert_eq!(args.num_threads, 6);
+        assert_eq!(args.url, "");
+        assert_eq!(args.checkpoint_interval, Duration::from_secs(10));
+
+        let args = vec![
+            "file".to_string(),
+            "http://example.com".to_string(),
+            "--resume".to_string(),
+            "./crawlings/2020-01-01/crawler.state".to_string(),
+            "6".to_string(),
+        ];
+        assert!(Args::new(&args).is_err());
+    }
+
+    #[test]
+    fn args_success_priority() {
+        let args = vec![
+            "file".to_string(),
+            "http://example.com".to_string(),
+            "./crawlings".to_string(),
+            "6".to_string(),
+            "--priority".to_string(),
+            "keywords:rust,crawler".to_string(),
+        ];
+        assert_eq!(
+            Args::new(&args).unwrap().priority,
+            Priority::Keywords(vec!["rust".to_string(), "crawler".to_string()])
+        );
+    }
+
+    #[test]
+    fn args_success_filter() {
+        let args = vec![
+            "file".to_string(),
+            "http://example.com".to_string(),
+            "./crawlings".to_string(),
+            "6".to_string(),
+            "--filter".to_string(),
+            "./filter.txt".to_string(),
+        ];
+        assert_eq!(Args::new(&args).unwrap().filter, Some("./filter.txt"));
+    }
+
+    #[test]
+    fn args_success_history() {
+        let args = vec![
+            "file".to_string(),
+            "http://example.com".to_string(),
+            "./crawlings".to_string(),
+            "6".to_string(),
+            "--history".to_string(),
+            "./crawlings/history".to_string(),
+        ];
+        assert_eq!(
+            Args::new(&args).unwrap().history,
+            Some("./crawlings/history")
+        );
+    }
+
+    #[test]
+    fn args_success_max_redirects() {
+        let args = vec![
+            "file".to_string(),
+            "http://example.com".to_string(),
+            "./crawlings".to_string(),
+            "6".to_string(),
+            "--max-redirects".to_string(),
+            "3".to_string(),
+        ];
+        assert_eq!(Args::new(&args).unwrap().max_redirects, Some(3));
+    }
+
+    #[test]
+    fn args_success_client() {
+        let args = vec![
+            "file".to_string(),
+            "http://example.com".to_string(),
+            "./crawlings".to_string(),
+            "6".to_string(),
+            "--user-agent".to_string(),
+            "MyBot/1.0".to_string(),
+            "--connect-timeout".to_string(),
+            "5".to_string(),
+            "--read-timeout".to_string(),
+            "10".to_string(),
+            "--timeout".to_string(),
+            "60".to_string(),
+            "--header".to_string(),
+            "Accept-Language: en".to_string(),
+            "--header".to_string(),
+            "X-Token:abc".to_string(),
+            "--proxy".to_string(),
+            "http://localhost:8080".to_string(),
+            "--insecure".to_string(),
+        ];
+        let args = Args::new(&args).unwrap();
+        assert_eq!(args.user_agent, Some("MyBot/1.0"));
+        assert_eq!(args.connect_timeout, Some(Duration::from_secs(5)));
+        assert_eq!(args.read_timeout, Some(Duration::from_secs(10)));
+        assert_eq!(args.timeout, Some(Duration::from_secs(60)));
+        assert_eq!(
+            args.headers,
+            vec![("Accept-Language", "en"), ("X-Token", "abc")]
+        );
+        assert_eq!(args.proxy, Some("http://localhost:8080"));
+        assert!(args.insecure);
+    }
+
+    #[test]
+    fn args_success_retries() {
+        let args = vec![
+            "file".to_string(),
+            "http://example.com".to_string(),
+            "./crawlings".to_string(),
+            "6".to_string(),
+            "--max-retries".to_string(),
+            "5".to_string(),
+            "--retry-delay".to_string(),
+            "250".to_string(),
+        ];
+        let args = Args::new(&args).unwrap();
+        assert_eq!(args.max_retries, Some(5));
+        assert_eq!(args.retry_delay, Some(Duration::from_millis(250)));
+    }
+
+    #[test]
+    fn args_success_size_limits() {
+        let args = vec![
+            "file".to_string(),
+            "http://example.com".to_string(),
+            "./crawlings".to_string(),
+            "6".to_string(),
+            "--max-size".to_string(),
+            "1000".to_string(),
+            "--max-size-for".to_string(),
+            "html:500".to_string(),
+            "--max-size-for".to_string(),
+            "application/pdf: 2000".to_string(),
+            "--truncate".to_string(),
+        ];
+        let args = Args::new(&args).unwrap();
+        assert_eq!(args.max_size, Some(1000));
+        assert_eq!(
+            args.max_sizes_for,
+            vec![("html", 500), ("application/pdf", 2000)]
+        );
+        assert!(args.truncate);
+
+        let args = vec![
+            "file".to_string(),
+            "http://example.com".to_string(),
+            "./crawlings".to_string(),
+            "6".to_string(),
+            "--max-size-for".to_string(),
+            "html".to_string(),
+        ];
+        assert!(Args::new(&args).is_err());
+    }
+
+    #[test]
+    fn args_failure_invalid_header() {
+        let args = vec![
+            "file".to_string(),
+            "http://example.com".to_string(),
+            "./crawlings".to_string(),
+            "6".to_string(),
+            "--header".to_string(),
+            "Accept-Language".to_string(),
+        ];
+        assert!(Args::new(&args)
+            .unwrap_err()
+            .to_string()
+            .contains("Invalid value for \"--header\""));
+    }
+
     #[test]
     fn args_failure_missing_arguments() {
         let args = vec!["file".to_string()];
         assert!(Args::new(&args).unwrap_err().to_string().contains("Usage:"));
     }
+
+    #[test]
+    fn args_failure_invalid_option() {
+        let args = vec![
+            "file".to_string(),
+            "http://example.com".to_string(),
+            "./crawlings".to_string(),
+            "6".to_string(),
+            "--max-depth".to_string(),
+            "foo".to_string(),
+        ];
+        assert!(Args::new(&args)
+            .unwrap_err()
+            .to_string()
+            .contains("--max-depth"));
+    }
 }
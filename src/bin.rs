@@ -1,7 +1,15 @@
 use crawler::args::Args;
 use crawler::crawler::Crawler;
+use crawler::crawling::SchemePolicy;
+use crawler::filter::UrlFilter;
+use crawler::retry::RetryPolicy;
+use crawler::robots::get_product_token;
 use crawler::shared::{self, FSPersister, MainFetcher};
 use std::env::args;
+use std::path::{Path, PathBuf};
+
+// the name of the state file inside of the output directory
+static CHECKPOINT_FILE: &str = "crawler.state";
 
 fn main() {
     run_binary().unwrap_or_else(|err| {
@@ -14,11 +22,104 @@ fn run_binary() -> shared::Result<()> {
     let arguments: Vec<String> = args().collect();
     let args = Args::new(&arguments)?;
 
-    let persister = FSPersister::new(args.out_dir)?;
-    let fetcher = MainFetcher::new();
+    // a resumed crawl continues to persist into the directory of its state file
+    let (persister, checkpoint) = match args.resume {
+        Some(state) => {
+            let out_dir = Path::new(state)
+                .parent()
+                .filter(|dir| !dir.as_os_str().is_empty())
+                .unwrap_or_else(|| Path::new("."));
+            (FSPersister::open(out_dir)?, PathBuf::from(state))
+        }
+        None => {
+            let persister = FSPersister::new(args.out_dir)?;
+            let checkpoint = persister.get_out_dir().join(CHECKPOINT_FILE);
+            (persister, checkpoint)
+        }
+    };
+    let mut builder = MainFetcher::builder()
+        .accept_invalid_certs(args.insecure)
+        .truncate(args.truncate);
+    if let Some(user_agent) = args.user_agent {
+        builder = builder.user_agent(user_agent);
+    }
+    if let Some(connect_timeout) = args.connect_timeout {
+        builder = builder.connect_timeout(Some(connect_timeout));
+    }
+    if let Some(read_timeout) = args.read_timeout {
+        builder = builder.read_timeout(Some(read_timeout));
+    }
+    if let Some(timeout) = args.timeout {
+        builder = builder.total_timeout(Some(timeout));
+    }
+    for (name, value) in args.headers.iter() {
+        builder = builder.header(name, value);
+    }
+    if let Some(proxy) = args.proxy {
+        builder = builder.proxy(proxy);
+    }
+    if let Some(max_size) = args.max_size {
+        builder = builder.max_size(Some(max_size));
+    }
+    for (content_type, max_size) in args.max_sizes_for.iter() {
+        builder = builder.max_size_for(content_type, *max_size);
+    }
+    let fetcher = builder.build()?;
 
     let mut crawler = Crawler::new(persister, fetcher, args.num_threads);
-    crawler.start(args.url)?;
+    crawler.set_max_depth(args.max_depth);
+    crawler.set_scope(args.scope);
+    if let Some(filter) = args.filter {
+        crawler.set_url_filter(UrlFilter::load(filter)?);
+    }
+    crawler.set_link_kinds(args.link_kinds);
+    if let Some(tracking_params) = args.tracking_params {
+        crawler.set_tracking_params(tracking_params);
+    }
+    crawler.set_scheme_policy(SchemePolicy {
+        collect_contacts: args.collect_contacts,
+        ..SchemePolicy::default()
+    });
+    crawler.set_respect_robots(!args.ignore_robots);
+    crawler.set_respect_nofollow(!args.ignore_nofollow);
+    if let Some(user_agent) = args.user_agent {
+        crawler.set_robots_user_agent(get_product_token(user_agent));
+    }
+    crawler.set_delay(args.delay);
+    crawler.set_max_per_host(args.max_per_host);
+    crawler.set_max_pages(args.max_pages);
+    crawler.set_max_bytes(args.max_bytes);
+    crawler.set_max_duration(args.max_duration);
+    if let Some(queue_buffer) = args.queue_buffer {
+        crawler.set_queue_buffer(queue_buffer);
+    }
+    if let Some(max_redirects) = args.max_redirects {
+        crawler.set_max_redirects(max_redirects);
+    }
+    let mut retry_policy = RetryPolicy::default();
+    if let Some(max_retries) = args.max_retries {
+        retry_policy.max_retries = max_retries;
+    }
+    if let Some(retry_delay) = args.retry_delay {
+        retry_policy.base_delay = retry_delay;
+    }
+    crawler.set_retry_policy(retry_policy);
+    if let Some(history) = args.history {
+        crawler.set_history(history);
+    }
+    crawler.set_scorer(args.priority.to_score());
+    crawler.set_checkpoint(&checkpoint.to_string_lossy(), args.checkpoint_interval);
+    let report = match args.resume {
+        Some(state) => crawler.resume(state)?,
+        None => crawler.start(args.url)?,
+    };
+    println!("{}", report);
+    for url in report.failed_urls.iter() {
+        println!("Failed to crawl {}", url);
+    }
+    for contact in report.contacts.iter() {
+        println!("{}", contact);
+    }
 
     Ok(())
 }
@@ -0,0 +1,177 @@
+use reqwest::Url;
+
+// query parameters which only track where a visitor came from. A trailing `*` matches any suffix
+static TRACKING_PARAMS: [&str; 7] = [
+    "utm_*", "gclid", "fbclid", "msclkid", "mc_cid", "mc_eid", "_ga",
+];
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct Canonicalizer {
+    tracking_params: Vec<String>,
+}
+
+impl Default for Canonicalizer {
+    fn default() -> Self {
+        let tracking_params = TRACKING_PARAMS.iter().map(|p| p.to_string()).collect();
+        Canonicalizer::new(tracking_params)
+    }
+}
+
+impl Canonicalizer {
+    pub fn new(tracking_params: Vec<String>) -> Self {
+        Canonicalizer { tracking_params }
+    }
+
+    // NOTE: parsing a `Url` already lowercases the host, removes default ports and resolves dot
+    // segments so that we only have to take care of the rest here
+    pub fn canonicalize(&self, url: &Url) -> Url {
+        let mut canonical = url.clone();
+        canonical.set_fragment(None);
+
+        if !canonical.cannot_be_a_base() {
+            let path = normalize_percent_encoding(canonical.path());
+            canonical.set_path(&path);
+        }
+
+        let query = canonical.query().map(|query| {
+            let mut params: Vec<String> = query
+                .split('&')
+                .filter(|param| !param.is_empty())
+                .map(normalize_percent_encoding)
+                .filter(|param| !self.is_tracking_param(param))
+                .collect();
+            params.sort();
+            params.join("&")
+        });
+        match query {
+            Some(query) if !query.is_empty() => canonical.set_query(Some(&query)),
+            _ => canonical.set_query(None),
+        }
+
+        canonical
+    }
+
+    fn is_tracking_param(&self, param: &str) -> bool {
+        let key = param.split('=').next().unwrap_or("");
+        self.tracking_params
+            .iter()
+            .any(|tracking| match tracking.strip_suffix('*') {
+                Some(prefix) => key.starts_with(prefix),
+                None => key == tracking,
+            })
+    }
+}
+
+// decodes percent-encoded unreserved characters and uppercases all other percent-encodings
+fn normalize_percent_encoding(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut normalized = String::with_capacity(value.len());
+    let mut pos = 0;
+    while pos < bytes.len() {
+        if bytes[pos] == b'%' && pos + 3 <= bytes.len() && is_hex_pair(&bytes[pos + 1..pos + 3]) {
+            let hex = &value[pos + 1..pos + 3];
+            let decoded = u8::from_str_radix(hex, 16).unwrap();
+            if decoded.is_ascii_alphanumeric() || b"-._~".contains(&decoded) {
+                normalized.push(decoded as char);
+            } else {
+                normalized.push('%');
+                normalized.push_str(&hex.to_ascii_uppercase());
+            }
+            pos += 3;
+        } else {
+            let c = value[pos..].chars().next().unwrap();
+            normalized.push(c);
+            pos += c.len_utf8();
+        }
+    }
+    normalized
+}
+
+fn is_hex_pair(bytes: &[u8]) -> bool {
+    bytes.len() == 2 && bytes.iter().all(|b| b.is_ascii_hexdigit())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::canonical::{normalize_percent_encoding, Canonicalizer};
+    use reqwest::Url;
+
+    fn canonicalize(url: &str) -> String {
+        Canonicalizer::default()
+            .canonicalize(&Url::parse(url).unwrap())
+            .to_string()
+    }
+
+    #[test]
+    fn canonicalize_host_port_and_fragment() {
+        assert_eq!(
+            canonicalize("http://EXAMPLE.com:80/a#x"),
+            "http://example.com/a"
+        );
+        assert_eq!(
+            canonicalize("https://example.com:443/"),
+            "https://example.com/"
+        );
+        assert_eq!(
+            canonicalize("http://example.com:8080/a"),
+            "http://example.com:8080/a"
+        );
+    }
+
+    #[test]
+    fn canonicalize_dot_segments() {
+        assert_eq!(
+            canonicalize("http://example.com/a/./b/../c"),
+            "http://example.com/a/c"
+        );
+    }
+
+    #[test]
+    fn canonicalize_query() {
+        assert_eq!(
+            canonicalize("http://example.com/a?b=1&a=2"),
+            canonicalize("http://example.com/a?a=2&b=1")
+        );
+        assert_eq!(
+            canonicalize("http://example.com/a?"),
+            "http://example.com/a"
+        );
+        assert_eq!(
+            canonicalize("http://example.com/a?utm_source=x&id=1&utm_medium=y&fbclid=z"),
+            "http://example.com/a?id=1"
+        );
+        assert_eq!(
+            canonicalize("http://example.com/a?utm_source=x"),
+            "http://example.com/a"
+        );
+    }
+
+    #[test]
+    fn canonicalize_custom_tracking_params() {
+        let canonicalizer = Canonicalizer::new(vec!["ref".to_string(), "session*".to_string()]);
+        let url = Url::parse("http://example.com/?ref=foo&sessionid=1&utm_source=x").unwrap();
+
+        assert_eq!(
+            canonicalizer.canonicalize(&url).to_string(),
+            "http://example.com/?utm_source=x"
+        );
+    }
+
+    #[test]
+    fn canonicalize_percent_encoding() {
+        assert_eq!(
+            canonicalize("http://example.com/%7efoo/%2fbar?q=%41%2c"),
+            "http://example.com/~foo/%2Fbar?q=A%2C"
+        );
+        assert_eq!(normalize_percent_encoding("%zz%4"), "%zz%4");
+        assert_eq!(normalize_percent_encoding("caf%C3%A9"), "caf%C3%A9");
+    }
+
+    #[test]
+    fn canonicalize_other_schemes() {
+        assert_eq!(
+            canonicalize("mailto:jdoe@example.com"),
+            "mailto:jdoe@example.com"
+        );
+    }
+}
@@ -0,0 +1,116 @@
+use crate::html::parse_tags;
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8, WINDOWS_1252, X_USER_DEFINED};
+use std::borrow::Cow;
+
+// the number of bytes which are searched for a `<meta>` tag declaring the encoding
+static PRESCAN_LEN: usize = 1024;
+
+// detects the encoding of an HTML page. A byte order mark wins over the `charset` of the
+// `Content-Type` header which wins over a `<meta>` tag in the first 1024 bytes of the page.
+// NOTE: pages which don't declare their encoding are treated as UTF-8
+pub fn detect(content_type: &str, content: &[u8]) -> &'static Encoding {
+    if let Some((encoding, _)) = Encoding::for_bom(content) {
+        return encoding;
+    }
+    get_charset(content_type)
+        .and_then(|charset| Encoding::for_label(charset.as_bytes()))
+        .or_else(|| prescan(content))
+        .unwrap_or(UTF_8)
+}
+
+// decodes the content into UTF-8 and removes the byte order mark. Invalid bytes are replaced
+pub fn decode<'a>(content: &'a [u8], encoding: &'static Encoding) -> Cow<'a, str> {
+    encoding.decode_with_bom_removal(content).0
+}
+
+// e.g. `shift_jis` for `text/html; charset="Shift_JIS"`
+fn get_charset(content_type: &str) -> Option<&str> {
+    content_type.split(';').skip(1).find_map(|param| {
+        let (name, value) = param.split_once('=')?;
+        if !name.trim().eq_ignore_ascii_case("charset") {
+            return None;
+        }
+        Some(value.trim().trim_matches(|c| c == '"' || c == '\''))
+            .filter(|charset| !charset.is_empty())
+    })
+}
+
+// looks for `<meta charset>` or `<meta http-equiv="Content-Type" content="...; charset=...">`.
+// The encodings which are declared in `<meta>` tags are ASCII compatible, so decoding the start of
+// the page as UTF-8 is good enough to find them
+fn prescan(content: &[u8]) -> Option<&'static Encoding> {
+    let start = String::from_utf8_lossy(&content[..content.len().min(PRESCAN_LEN)]);
+    let encoding = parse_tags(&start)
+        .iter()
+        .filter(|tag| tag.name == "meta")
+        .find_map(|tag| {
+            let charset = tag.get_attribute("charset").or_else(|| {
+                let is_content_type = tag
+                    .get_attribute("http-equiv")
+                    .is_some_and(|value| value.eq_ignore_ascii_case("content-type"));
+                if !is_content_type {
+                    return None;
+                }
+                // e.g. `text/html; charset=utf-8`
+                tag.get_attribute("content").and_then(get_charset)
+            })?;
+            Encoding::for_label(charset.trim().as_bytes())
+        })?;
+    // a page can't declare a non ASCII compatible encoding in ASCII, the HTML spec falls back to
+    // UTF-8 and windows-1252 for these
+    if encoding == UTF_16LE || encoding == UTF_16BE {
+        return Some(UTF_8);
+    }
+    if encoding == X_USER_DEFINED {
+        return Some(WINDOWS_1252);
+    }
+    Some(encoding)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::charset::{decode, detect};
+    use encoding_rs::{SHIFT_JIS, UTF_16LE, UTF_8, WINDOWS_1252};
+
+    #[test]
+    fn charset_detect() {
+        assert_eq!(detect("text/html", b"Hello"), UTF_8);
+        assert_eq!(
+            detect("text/html; charset=\"Shift_JIS\"", b"Hello"),
+            SHIFT_JIS
+        );
+        assert_eq!(detect("text/html; charset=latin1", b"Hello"), WINDOWS_1252);
+        assert_eq!(detect("text/html; charset=foo", b"Hello"), UTF_8);
+        assert_eq!(detect("text/html", b"\xFF\xFEH\x00"), UTF_16LE);
+        assert_eq!(
+            detect("text/html; charset=shift_jis", b"\xEF\xBB\xBFHello"),
+            UTF_8
+        );
+    }
+
+    #[test]
+    fn charset_detect_meta() {
+        let html = b"<html><head><meta charset=\"windows-1252\"></head></html>";
+        assert_eq!(detect("text/html", html), WINDOWS_1252);
+        // the header wins
+        assert_eq!(detect("text/html; charset=utf-8", html), UTF_8);
+
+        let html = b"<meta http-equiv=\"Content-Type\" content=\"text/html; charset=Shift_JIS\">";
+        assert_eq!(detect("text/html", html), SHIFT_JIS);
+
+        let html = b"<meta charset=\"utf-16\">";
+        assert_eq!(detect("text/html", html), UTF_8);
+
+        // declarations beyond the first 1024 bytes are ignored
+        let mut html = vec![b' '; 1024];
+        html.extend_from_slice(b"<meta charset=\"windows-1252\">");
+        assert_eq!(detect("text/html", &html), UTF_8);
+    }
+
+    #[test]
+    fn charset_decode() {
+        assert_eq!(decode(b"Caf\xE9", WINDOWS_1252), "Café");
+        assert_eq!(decode(b"\x83n\x83\x8D\x81[", SHIFT_JIS), "ハロー");
+        assert_eq!(decode(b"\xEF\xBB\xBFCaf\xC3\xA9", UTF_8), "Café");
+    }
+}
@@ -0,0 +1,201 @@
+use crate::crawler::CrawlReport;
+use crate::error::CrawlerError::ParsingError;
+use crate::job::{Queue, Spill};
+use crate::shared;
+use reqwest::Url;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::time::Duration;
+
+// the state of a crawl which is needed to continue it later on. The frontier and the seen set are
+// stored together with it but live in the `Queue`
+#[derive(PartialEq, Debug, Clone)]
+pub struct Checkpoint {
+    pub seed: Url,
+    // the counters of the report so far. The stop reason isn't stored
+    pub report: CrawlReport,
+}
+
+impl Checkpoint {
+    pub fn new(seed: Url) -> Self {
+        Checkpoint {
+            seed,
+            report: CrawlReport::default(),
+        }
+    }
+
+    // the file is replaced at once so that a crash while writing leaves the last state intact
+    pub fn write<'a, T, I>(
+        &self,
+        path: &Path,
+        queue: &mut Queue<T>,
+        values: I,
+    ) -> shared::Result<()>
+    where
+        T: Eq + Clone + Spill + 'a,
+        I: IntoIterator<Item = &'a T>,
+    {
+        let tmp_path = path.with_extension("tmp");
+        let mut writer = BufWriter::new(File::create(&tmp_path)?);
+        writeln!(writer, "seed {}", self.seed)?;
+        let report = &self.report;
+        // the pages which count towards `max_pages`, so a resumed crawl continues the same limit
+        writeln!(writer, "fetched {}", report.fetched)?;
+        writeln!(writer, "persisted {}", report.persisted)?;
+        writeln!(writer, "retries {}", report.retries)?;
+        writeln!(writer, "truncated {}", report.truncated)?;
+        writeln!(writer, "changed {}", report.changed)?;
+        writeln!(writer, "unchanged {}", report.unchanged)?;
+        writeln!(writer, "bytes {}", report.bytes)?;
+        writeln!(writer, "elapsed {}", report.elapsed.as_millis())?;
+        for (reason, count) in report.skipped.iter() {
+            writeln!(writer, "skipped {} {}", reason, count)?;
+        }
+        for (kind, count) in report.failed.iter() {
+            writeln!(writer, "failed {} {}", kind, count)?;
+        }
+        for url in report.failed_urls.iter() {
+            writeln!(writer, "failed_url {}", url)?;
+        }
+        for contact in report.contacts.iter() {
+            writeln!(writer, "contact {}", contact)?;
+        }
+        queue.write_to(&mut writer, values)?;
+        writer.flush()?;
+        drop(writer);
+        fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+
+    // restores the frontier and the seen set into the queue
+    pub fn read<T>(path: &Path, queue: &mut Queue<T>) -> shared::Result<Self>
+    where
+        T: Eq + Clone + Spill,
+    {
+        let reader = BufReader::new(File::open(path)?);
+        let mut seed = None;
+        let mut report = CrawlReport::default();
+        for (idx, line) in reader.lines().enumerate() {
+            let line = line?;
+            let invalid = || ParsingError(format!("Invalid checkpoint in line {}", idx + 1));
+            let (key, value) = line.split_once(' ').ok_or_else(invalid)?;
+            match key {
+                "seed" => seed = Some(Url::parse(value).map_err(|_| invalid())?),
+                "fetched" => report.fetched = value.parse().map_err(|_| invalid())?,
+                "persisted" => report.persisted = value.parse().map_err(|_| invalid())?,
+                "retries" => report.retries = value.parse().map_err(|_| invalid())?,
+                "truncated" => report.truncated = value.parse().map_err(|_| invalid())?,
+                "changed" => report.changed = value.parse().map_err(|_| invalid())?,
+                "unchanged" => report.unchanged = value.parse().map_err(|_| invalid())?,
+                "bytes" => report.bytes = value.parse().map_err(|_| invalid())?,
+                "elapsed" => {
+                    let millis = value.parse().map_err(|_| invalid())?;
+                    report.elapsed = Duration::from_millis(millis);
+                }
+                "skipped" => {
+                    let (reason, count) = value.split_once(' ').ok_or_else(invalid)?;
+                    let reason = reason.parse().map_err(|_| invalid())?;
+                    report
+                        .skipped
+                        .insert(reason, count.parse().map_err(|_| invalid())?);
+                }
+                "failed" => {
+                    let (kind, count) = value.split_once(' ').ok_or_else(invalid)?;
+                    report
+                        .failed
+                        .insert(kind.to_string(), count.parse().map_err(|_| invalid())?);
+                }
+                "failed_url" => report
+                    .failed_urls
+                    .push(Url::parse(value).map_err(|_| invalid())?),
+                "contact" => report.contacts.push(value.parse().map_err(|_| invalid())?),
+                _ => queue.read_line(&line).ok_or_else(invalid)?,
+            }
+        }
+        Ok(Checkpoint {
+            seed: seed.ok_or_else(|| ParsingError("Checkpoint without seed".to_string()))?,
+            report,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::checkpoint::Checkpoint;
+    use crate::crawler::{CrawlReport, SkipReason};
+    use crate::crawling::{Contact, SchemePolicy};
+    use crate::job::{Job, Queue};
+    use crate::traits::Fetch;
+    use reqwest::Url;
+    use std::env;
+    use std::fs;
+    use std::process;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[derive(Eq, PartialEq, Hash, Clone, Debug, Default)]
+    struct MockFetcher;
+    impl Fetch for MockFetcher {}
+
+    fn to_job(url: &str, depth: usize) -> Job<MockFetcher> {
+        Job::new(
+            Arc::new(MockFetcher),
+            Url::parse(url).unwrap(),
+            depth,
+            &SchemePolicy::default(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn checkpoint_write_and_read() {
+        let path = env::temp_dir().join(format!("crawler-checkpoint-{}", process::id()));
+        let checkpoint = Checkpoint {
+            seed: Url::parse("http://example.com/").unwrap(),
+            report: CrawlReport {
+                fetched: 2,
+                persisted: 1,
+                skipped: vec![(SkipReason::Scope, 3), (SkipReason::Noindex, 1)]
+                    .into_iter()
+                    .collect(),
+                failed: vec![("RequestError".to_string(), 1)].into_iter().collect(),
+                failed_urls: vec![Url::parse("http://example.com/missing").unwrap()],
+                retries: 2,
+                truncated: 1,
+                changed: 3,
+                unchanged: 4,
+                bytes: 1024,
+                elapsed: Duration::from_millis(1500),
+                contacts: vec![
+                    Contact::Email("jdoe@example.com".to_string()),
+                    Contact::Phone("+1-555-0100".to_string()),
+                ],
+                ..CrawlReport::default()
+            },
+        };
+
+        let mut queue = Queue::new(10, Arc::new(MockFetcher));
+        queue.enqueue(to_job("http://example.com/", 0));
+        queue.dequeue();
+        queue.enqueue(to_job("http://example.com/about", 1));
+        let in_flight = to_job("http://example.com/imprint", 1);
+        checkpoint
+            .write(&path, &mut queue, vec![&in_flight])
+            .unwrap();
+
+        let mut restored = Queue::new(10, Arc::new(MockFetcher));
+        assert_eq!(Checkpoint::read(&path, &mut restored).unwrap(), checkpoint);
+        assert!(restored.has_seen(&to_job("http://example.com/", 0)));
+        assert_eq!(restored.dequeue(), Some(in_flight));
+        let job = restored.dequeue().unwrap();
+        assert_eq!(job.get_url().as_str(), "http://example.com/about");
+        assert_eq!(job.get_depth(), 1);
+        assert_eq!(restored.dequeue(), None);
+
+        fs::write(&path, "seed http://example.com/\nfetched foo\n").unwrap();
+        let err = Checkpoint::read(&path, &mut restored);
+        assert!(err.unwrap_err().to_string().contains("line 2"));
+        fs::remove_file(&path).unwrap();
+    }
+}
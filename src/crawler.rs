@@ -1,15 +1,297 @@
-use crate::crawling::Crawling;
-use crate::error::CrawlerError::FetchingError;
+use crate::canonical::Canonicalizer;
+use crate::checkpoint::Checkpoint;
+use crate::crawling::{Contact, Crawling, Link, LinkKind, SchemePolicy};
+use crate::error::CrawlerError::{self, ParsingError, RequestError, WorkerError};
+use crate::filter::UrlFilter;
+use crate::history::{self, History, Revision};
 use crate::job::{Job, Queue};
+use crate::politeness::HostScheduler;
+use crate::response::{Response, MAX_REDIRECTS};
+use crate::retry::{self, RetryPolicy};
+use crate::robots::RobotsCache;
+use crate::scope::Scope;
 use crate::shared::{self, CrawlingResult};
-use crate::traits::{Fetch, Persist};
+use crate::traits::{Fetch, Observe, Persist, Score};
 use reqwest::Url;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt::{self, Display, Formatter};
 use std::hash::Hash;
-use std::sync::Arc;
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
 use std::thread;
+use std::time::{Duration, Instant};
 
-// TODO: make this configurable from the outside
 static QUEUE_BUFFER: usize = 1_000_000;
+static ROBOTS_USER_AGENT: &str = "crawler";
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum StopReason {
+    Finished,
+    MaxPages,
+    MaxBytes,
+    MaxDuration,
+    // the observer asked to stop (see `Observe::should_stop`)
+    Stopped,
+}
+
+// why a found link wasn't turned into a job or a fetched page wasn't persisted
+#[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Clone, Copy)]
+pub enum SkipReason {
+    // the kind of link isn't crawled (see `set_link_kinds`)
+    Kind,
+    Nofollow,
+    Depth,
+    Scope,
+    Filter,
+    Robots,
+    Scheme,
+    // a redirect led to a page which was already seen
+    Duplicate,
+    // the persister declined the page, e.g. because of a `noindex` directive
+    Noindex,
+}
+
+impl Display for SkipReason {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let reason = match self {
+            SkipReason::Kind => "kind",
+            SkipReason::Nofollow => "nofollow",
+            SkipReason::Depth => "depth",
+            SkipReason::Scope => "scope",
+            SkipReason::Filter => "filter",
+            SkipReason::Robots => "robots",
+            SkipReason::Scheme => "scheme",
+            SkipReason::Duplicate => "duplicate",
+            SkipReason::Noindex => "noindex",
+        };
+        write!(f, "{}", reason)
+    }
+}
+
+impl FromStr for SkipReason {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "kind" => Ok(SkipReason::Kind),
+            "nofollow" => Ok(SkipReason::Nofollow),
+            "depth" => Ok(SkipReason::Depth),
+            "scope" => Ok(SkipReason::Scope),
+            "filter" => Ok(SkipReason::Filter),
+            "robots" => Ok(SkipReason::Robots),
+            "scheme" => Ok(SkipReason::Scheme),
+            "duplicate" => Ok(SkipReason::Duplicate),
+            "noindex" => Ok(SkipReason::Noindex),
+            _ => Err(format!("Unknown skip reason \"{}\"", value)),
+        }
+    }
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct CrawlReport {
+    pub stop_reason: StopReason,
+    // pages which were fetched, whether they were persisted or not
+    pub fetched: usize,
+    pub persisted: usize,
+    pub skipped: BTreeMap<SkipReason, usize>,
+    // the number of failed pages by the kind of error (see `CrawlerError::get_kind`). A page which
+    // was fetched but couldn't be persisted counts as fetched and as failed
+    pub failed: BTreeMap<String, usize>,
+    pub failed_urls: Vec<Url>,
+    // fetches which failed for a transient reason and were tried again
+    pub retries: usize,
+    // fetched pages of which only the start was kept because they exceeded the size limit
+    pub truncated: usize,
+    // pages of a previous crawl which were fetched again because they changed
+    pub changed: usize,
+    // pages of a previous crawl which didn't change. They're neither fetched nor persisted again
+    pub unchanged: usize,
+    pub bytes: usize,
+    pub elapsed: Duration,
+    // only collected if the scheme policy asks for it
+    pub contacts: Vec<Contact>,
+}
+
+impl Default for CrawlReport {
+    fn default() -> Self {
+        CrawlReport {
+            stop_reason: StopReason::Finished,
+            fetched: 0,
+            persisted: 0,
+            skipped: BTreeMap::new(),
+            failed: BTreeMap::new(),
+            failed_urls: vec![],
+            retries: 0,
+            truncated: 0,
+            changed: 0,
+            unchanged: 0,
+            bytes: 0,
+            elapsed: Duration::from_secs(0),
+            contacts: vec![],
+        }
+    }
+}
+
+impl CrawlReport {
+    pub fn count_skipped(&self) -> usize {
+        self.skipped.values().sum()
+    }
+
+    pub fn count_failed(&self) -> usize {
+        self.failed.values().sum()
+    }
+}
+
+impl Display for CrawlReport {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let reason = match self.stop_reason {
+            StopReason::Finished => "no URLs left",
+            StopReason::MaxPages => "page limit reached",
+            StopReason::MaxBytes => "byte limit reached",
+            StopReason::MaxDuration => "time limit reached",
+            StopReason::Stopped => "stopped by observer",
+        };
+        writeln!(
+            f,
+            "Stopped crawling ({}) after fetching {} pages with {} bytes in {:.2}s",
+            reason,
+            self.fetched,
+            self.bytes,
+            self.elapsed.as_secs_f64()
+        )?;
+        write!(f, "Persisted {} pages", self.persisted)?;
+        if self.truncated > 0 {
+            write!(f, "\nTruncated {} pages", self.truncated)?;
+        }
+        if self.changed + self.unchanged > 0 {
+            write!(
+                f,
+                "\nRevalidated {} pages ({} changed, {} unchanged)",
+                self.changed + self.unchanged,
+                self.changed,
+                self.unchanged
+            )?;
+        }
+        if !self.skipped.is_empty() {
+            let skipped: Vec<String> = self
+                .skipped
+                .iter()
+                .map(|(reason, count)| format!("{} {}", reason, count))
+                .collect();
+            write!(
+                f,
+                "\nSkipped {} ({})",
+                self.count_skipped(),
+                skipped.join(", ")
+            )?;
+        }
+        if !self.failed.is_empty() {
+            let failed: Vec<String> = self
+                .failed
+                .iter()
+                .map(|(kind, count)| format!("{} {}", kind, count))
+                .collect();
+            write!(
+                f,
+                "\nFailed {} ({})",
+                self.count_failed(),
+                failed.join(", ")
+            )?;
+        }
+        if self.retries > 0 {
+            write!(f, "\nRetried {} fetches", self.retries)?;
+        }
+        Ok(())
+    }
+}
+
+// the observer used unless another one is set
+struct NoopObserver;
+impl Observe for NoopObserver {}
+
+// the rules every worker applies before turning a found URL into a new job
+struct Rules {
+    seed: Url,
+    max_depth: Option<usize>,
+    max_redirects: usize,
+    scope: Scope,
+    filter: UrlFilter,
+    link_kinds: Vec<LinkKind>,
+    canonicalizer: Canonicalizer,
+    scheme_policy: SchemePolicy,
+    // `None` if `robots.txt` should be ignored
+    robots: Option<RobotsCache>,
+    respect_nofollow: bool,
+}
+
+// what a worker reports back after crawling a single job
+struct WorkerResult<B>
+where
+    B: Fetch,
+{
+    url: Url,
+    host: String,
+    jobs: Option<Vec<Job<B>>>,
+    contacts: Option<Vec<Contact>>,
+    skipped: BTreeMap<SkipReason, usize>,
+    // `None` if the job couldn't be fetched
+    bytes: Option<usize>,
+    truncated: bool,
+    persisted: bool,
+    error: Option<CrawlerError>,
+    // how long the server asked to wait before trying again
+    retry_after: Option<Duration>,
+    // the page was crawled before
+    revalidated: bool,
+    unchanged: bool,
+    revision: Option<Revision>,
+}
+
+// what a worker sends back to the dispatcher
+enum WorkerMessage<B>
+where
+    B: Fetch,
+{
+    // asks whether the target of a redirect of the job with the first URL wasn't seen yet. It's
+    // marked as seen either way
+    Redirect(Url, Url, mpsc::Sender<bool>),
+    Done(WorkerResult<B>),
+    // a worker died and the job it was crawling will never be done
+    Panicked,
+}
+
+// tells the dispatcher when a worker panics so that it doesn't wait for the worker's job forever
+struct PanicGuard<B>(mpsc::Sender<WorkerMessage<B>>)
+where
+    B: Fetch;
+
+impl<B> Drop for PanicGuard<B>
+where
+    B: Fetch,
+{
+    fn drop(&mut self) {
+        if thread::panicking() {
+            self.0.send(WorkerMessage::Panicked).unwrap_or(());
+        }
+    }
+}
+
+enum Crawled<A, B>
+where
+    A: Persist,
+    B: Fetch,
+{
+    Page(Box<CrawlingResult<A, B>>),
+    // a redirect was refused
+    Skipped(SkipReason),
+    // e.g. a `404` or a `503` which might be retried
+    Unsuccessful(Box<Response>),
+    // the page didn't change since the previous crawl. Only the jobs of its links are known
+    Unchanged(Vec<Job<B>>, BTreeMap<SkipReason, usize>),
+}
 
 pub struct Crawler<A, B>
 where
@@ -18,6 +300,25 @@ where
 {
     queue: Queue<Job<B>>,
     num_threads: usize,
+    max_depth: Option<usize>,
+    max_redirects: usize,
+    scope: Scope,
+    filter: UrlFilter,
+    link_kinds: Vec<LinkKind>,
+    canonicalizer: Canonicalizer,
+    scheme_policy: SchemePolicy,
+    respect_robots: bool,
+    respect_nofollow: bool,
+    robots_user_agent: String,
+    delay: Duration,
+    max_per_host: Option<usize>,
+    max_pages: Option<usize>,
+    max_bytes: Option<usize>,
+    max_duration: Option<Duration>,
+    retry_policy: RetryPolicy,
+    history: Option<PathBuf>,
+    checkpoint: Option<(PathBuf, Duration)>,
+    observer: Arc<dyn Observe>,
     persister: Arc<A>,
     fetcher: Arc<B>,
 }
@@ -28,14 +329,144 @@ where
     B: Fetch + Eq + Clone + Hash + Send + Sync,
 {
     pub fn new(persister: A, fetcher: B, num_threads: usize) -> Self {
+        let fetcher = Arc::new(fetcher);
         Crawler {
-            queue: Queue::new(QUEUE_BUFFER),
+            queue: Queue::new(QUEUE_BUFFER, fetcher.clone()),
             persister: Arc::new(persister),
-            fetcher: Arc::new(fetcher),
+            fetcher,
             num_threads,
+            max_depth: None,
+            max_redirects: MAX_REDIRECTS,
+            scope: Scope::default(),
+            filter: UrlFilter::default(),
+            link_kinds: LinkKind::defaults(),
+            canonicalizer: Canonicalizer::default(),
+            scheme_policy: SchemePolicy::default(),
+            respect_robots: true,
+            respect_nofollow: true,
+            robots_user_agent: ROBOTS_USER_AGENT.to_string(),
+            delay: Duration::from_secs(0),
+            max_per_host: None,
+            max_pages: None,
+            max_bytes: None,
+            max_duration: None,
+            retry_policy: RetryPolicy::default(),
+            history: None,
+            checkpoint: None,
+            observer: Arc::new(NoopObserver),
         }
     }
 
+    // the starting URL has a depth of `0`, the pages it links to a depth of `1` and so on
+    pub fn set_max_depth(&mut self, max_depth: Option<usize>) {
+        self.max_depth = max_depth;
+    }
+
+    // the number of redirects followed per page. The target of every redirect has to pass the same
+    // checks as a found link and pages which were already seen through another URL are skipped
+    pub fn set_max_redirects(&mut self, max_redirects: usize) {
+        self.max_redirects = max_redirects;
+    }
+
+    pub fn set_scope(&mut self, scope: Scope) {
+        self.scope = scope;
+    }
+
+    // replaces the default filter which skips e.g. images, stylesheets and a few big sites
+    pub fn set_url_filter(&mut self, filter: UrlFilter) {
+        self.filter = filter;
+    }
+
+    // the kinds of links (e.g. `<a href>` or `<img src>`) which are turned into new jobs
+    pub fn set_link_kinds(&mut self, link_kinds: Vec<LinkKind>) {
+        self.link_kinds = link_kinds;
+    }
+
+    // query parameters which are removed from every URL before it's crawled. A trailing `*`
+    // matches any suffix, e.g. `utm_*`
+    pub fn set_tracking_params(&mut self, tracking_params: Vec<String>) {
+        self.canonicalizer = Canonicalizer::new(tracking_params);
+    }
+
+    // NOTE: jobs are only ever created for `http` and `https` URLs no matter which other schemes
+    // are admitted
+    pub fn set_scheme_policy(&mut self, scheme_policy: SchemePolicy) {
+        self.scheme_policy = scheme_policy;
+    }
+
+    // NOTE: only ignore `robots.txt` when crawling sites you're responsible for
+    pub fn set_respect_robots(&mut self, respect_robots: bool) {
+        self.respect_robots = respect_robots;
+    }
+
+    // `nofollow` directives of pages and `rel="nofollow"` links are honored regardless of
+    // `robots.txt`
+    pub fn set_respect_nofollow(&mut self, respect_nofollow: bool) {
+        self.respect_nofollow = respect_nofollow;
+    }
+
+    // the user agent used to pick the matching group of rules in `robots.txt`
+    pub fn set_robots_user_agent(&mut self, user_agent: &str) {
+        self.robots_user_agent = user_agent.to_string();
+    }
+
+    // the minimum time between two requests to the same host. A longer `Crawl-delay` from
+    // `robots.txt` is used instead once it's known
+    pub fn set_delay(&mut self, delay: Duration) {
+        self.delay = delay;
+    }
+
+    pub fn set_max_per_host(&mut self, max_per_host: Option<usize>) {
+        self.max_per_host = max_per_host;
+    }
+
+    pub fn set_max_pages(&mut self, max_pages: Option<usize>) {
+        self.max_pages = max_pages;
+    }
+
+    pub fn set_max_bytes(&mut self, max_bytes: Option<usize>) {
+        self.max_bytes = max_bytes;
+    }
+
+    pub fn set_max_duration(&mut self, max_duration: Option<Duration>) {
+        self.max_duration = max_duration;
+    }
+
+    // how often and after which delay jobs which failed for a transient reason are tried again.
+    // They're put back into the queue once the delay is over so that no worker waits for them
+    pub fn set_retry_policy(&mut self, retry_policy: RetryPolicy) {
+        self.retry_policy = retry_policy;
+    }
+
+    // the `ETag`, the `Last-Modified` and the links of every page are kept in `path` for the next
+    // crawl. Pages which were crawled before are only fetched and persisted again if they changed
+    pub fn set_history(&mut self, path: &str) {
+        self.history = Some(PathBuf::from(path));
+    }
+
+    // the state of the crawl is written to `path` every `interval` and once it's done so that it
+    // can be resumed later on
+    pub fn set_checkpoint(&mut self, path: &str, interval: Duration) {
+        self.checkpoint = Some((PathBuf::from(path), interval));
+    }
+
+    // the number of jobs kept in memory. Any further jobs are spilled to disk until there's room
+    // again
+    pub fn set_queue_buffer(&mut self, buffer: usize) {
+        self.queue.set_buffer(buffer);
+    }
+
+    // decides which of the found URLs are crawled first (see `priority` for the built-in ones).
+    // NOTE: a crawl has to be resumed with the same scorer, which is set before `resume`
+    pub fn set_scorer(&mut self, scorer: Box<dyn Score<Job<B>>>) {
+        self.queue.set_scorer(scorer);
+    }
+
+    // gets notified about every enqueued, fetched and persisted page and can stop the crawl
+    pub fn set_observer(&mut self, observer: Arc<dyn Observe>) {
+        self.observer = observer;
+    }
+
     pub fn get_persister(&self) -> Arc<A> {
         self.persister.clone()
     }
@@ -44,75 +475,632 @@ where
         self.fetcher.clone()
     }
 
-    pub fn start(&mut self, url: &str) -> shared::Result<()> {
-        let url = Url::parse(url)?;
-        let initial_job = Job::new(self.fetcher.clone(), url).unwrap();
-        self.queue.enqueue(initial_job);
+    pub fn start(&mut self, url: &str) -> shared::Result<CrawlReport> {
+        let url = self.canonicalizer.canonicalize(&Url::parse(url)?);
+        let initial_job = Some(url.clone())
+            .filter(|url| self.filter.allows(url))
+            .and_then(|url| Job::new(self.fetcher.clone(), url, 0, &self.scheme_policy))
+            .ok_or_else(|| ParsingError(format!("Can't crawl URL \"{}\"", url)))?;
+        if self.queue.enqueue(initial_job) {
+            self.observer.on_enqueue(&url, 0);
+        }
+        self.run(Checkpoint::new(url))
+    }
 
-        loop {
-            let mut handlers = Vec::with_capacity(self.num_threads);
-            for _ in 0..self.num_threads {
-                if let Some(job) = self.queue.dequeue() {
-                    let persister = self.persister.clone();
-                    let fetcher = self.fetcher.clone();
-                    let handler = thread::spawn(move || {
-                        if let Ok(result) = crawl(persister, fetcher, job) {
-                            result.crawling.write().unwrap_or_else(|_| 0);
-                            return result.jobs;
+    // continues a crawl from a checkpoint. Pages which were crawled before aren't fetched again
+    pub fn resume(&mut self, path: &str) -> shared::Result<CrawlReport> {
+        let checkpoint = Checkpoint::read(Path::new(path), &mut self.queue)?;
+        self.run(checkpoint)
+    }
+
+    fn run(&mut self, mut state: Checkpoint) -> shared::Result<CrawlReport> {
+        let rules = Arc::new(Rules {
+            seed: state.seed.clone(),
+            max_depth: self.max_depth,
+            max_redirects: self.max_redirects,
+            scope: self.scope.clone(),
+            filter: self.filter.clone(),
+            link_kinds: self.link_kinds.clone(),
+            canonicalizer: self.canonicalizer.clone(),
+            scheme_policy: self.scheme_policy.clone(),
+            robots: if self.respect_robots {
+                Some(RobotsCache::new(
+                    &self.robots_user_agent,
+                    self.observer.clone(),
+                ))
+            } else {
+                None
+            },
+            respect_nofollow: self.respect_nofollow,
+        });
+
+        let mut history = match &self.history {
+            Some(path) => Some(History::open(path)?),
+            None => None,
+        };
+
+        // the workers share a single job channel and report back through the results channel
+        let (job_sender, job_receiver) = mpsc::channel::<Job<B>>();
+        let (result_sender, result_receiver) = mpsc::channel::<WorkerMessage<B>>();
+        let job_receiver = Arc::new(Mutex::new(job_receiver));
+
+        let mut handlers = Vec::with_capacity(self.num_threads);
+        for _ in 0..self.num_threads {
+            let job_receiver = job_receiver.clone();
+            let result_sender = result_sender.clone();
+            let persister = self.persister.clone();
+            let fetcher = self.fetcher.clone();
+            let rules = rules.clone();
+            let observer = self.observer.clone();
+            let handler = thread::spawn(move || {
+                let _guard = PanicGuard(result_sender.clone());
+                loop {
+                    // the lock is released as soon as the next job was received
+                    let job = match job_receiver.lock().unwrap().recv() {
+                        Ok(job) => job,
+                        Err(_) => break,
+                    };
+                    let url = job.get_url();
+                    let host = get_host(&url);
+                    let mut result = WorkerResult {
+                        url,
+                        host,
+                        jobs: None,
+                        contacts: None,
+                        skipped: BTreeMap::new(),
+                        bytes: None,
+                        truncated: false,
+                        persisted: false,
+                        error: None,
+                        retry_after: None,
+                        revalidated: job.get_revision().is_some(),
+                        unchanged: false,
+                        revision: None,
+                    };
+                    // the dispatcher owns the seen set and answers right away
+                    let claim = |url: &Url| {
+                        let (reply_sender, reply_receiver) = mpsc::channel();
+                        let message =
+                            WorkerMessage::Redirect(result.url.clone(), url.clone(), reply_sender);
+                        result_sender.send(message).is_ok()
+                            && reply_receiver.recv().unwrap_or(false)
+                    };
+                    let crawled = crawl(
+                        persister.clone(),
+                        fetcher.clone(),
+                        job,
+                        &rules,
+                        &*observer,
+                        &claim,
+                    );
+                    match crawled {
+                        Ok(Crawled::Skipped(reason)) => {
+                            result.skipped.insert(reason, 1);
                         }
-                        None
-                    });
-                    handlers.push(handler);
+                        Ok(Crawled::Unchanged(jobs, skipped)) => {
+                            result.jobs = Some(jobs);
+                            result.skipped = skipped;
+                            result.unchanged = true;
+                        }
+                        Ok(Crawled::Unsuccessful(response)) => {
+                            result.error = Some(response.to_error());
+                            result.retry_after = retry::get_retry_after(&response.headers);
+                        }
+                        Ok(Crawled::Page(crawled)) => {
+                            result.bytes = Some(crawled.crawling.get_content().len());
+                            result.truncated = crawled.crawling.is_truncated();
+                            result.jobs = crawled.jobs;
+                            result.contacts = crawled.contacts;
+                            result.skipped = crawled.skipped;
+                            result.revision = crawled.revision;
+                            if !crawled.crawling.should_persist() {
+                                *result.skipped.entry(SkipReason::Noindex).or_insert(0) += 1;
+                            } else {
+                                match crawled.crawling.write() {
+                                    Ok(len) => {
+                                        observer.on_persist(&result.url, len);
+                                        result.persisted = true;
+                                    }
+                                    Err(err) => result.error = Some(err),
+                                }
+                            }
+                        }
+                        Err(err) => result.error = Some(err),
+                    }
+                    if let Some(err) = &result.error {
+                        observer.on_error(&result.url, err);
+                    }
+                    if result_sender.send(WorkerMessage::Done(result)).is_err() {
+                        break;
+                    }
+                }
+            });
+            handlers.push(handler);
+        }
+        // only the workers hold a sender, so the channel is disconnected once all of them are gone
+        drop(result_sender);
+
+        // the elapsed time and all counters continue where the checkpoint left off
+        let started = Instant::now();
+        let elapsed_before = state.report.elapsed;
+        let mut last_checkpoint = started;
+        let mut scheduler = HostScheduler::new(self.delay, self.max_per_host);
+        let mut stop_reason = None;
+        let mut contacts: BTreeSet<Contact> = state.report.contacts.drain(..).collect();
+        // jobs which were sent to a worker but aren't done yet
+        let mut in_flight: HashMap<Url, Job<B>> = HashMap::new();
+        // jobs which failed for a transient reason and when to try them again
+        let mut retrying: Vec<(Instant, Job<B>)> = vec![];
+        // the redirect targets claimed by each unfinished job. A retried job may claim them again
+        let mut claims: HashMap<Url, Vec<Url>> = HashMap::new();
+        loop {
+            // keep every worker busy as long as there's work and no limit was hit
+            let now = Instant::now();
+            if stop_reason.is_none() {
+                for (_, job) in drain_due(&mut retrying, now) {
+                    self.queue.requeue(job);
+                }
+            }
+            while stop_reason.is_none() && in_flight.len() < self.num_threads {
+                if self.queue.is_empty() {
+                    break;
+                }
+                let elapsed = elapsed_before + started.elapsed();
+                let report = &state.report;
+                stop_reason = self.exceeded_limit(report.fetched, report.bytes, elapsed);
+                if stop_reason.is_some() {
+                    break;
                 }
+                // in-flight jobs might still fail, be retried or be skipped, so they only hold
+                // back further jobs until they're done
+                let pages = report.fetched + in_flight.len();
+                if self.max_pages.is_some_and(|max_pages| pages >= max_pages) {
+                    break;
+                }
+                // jobs of hosts which aren't ready stay in the queue so that their order is kept
+                let mut job = match self
+                    .queue
+                    .dequeue_where(|job| scheduler.is_ready(&get_host(&job.get_url()), now))
+                {
+                    Some(job) => job,
+                    None => break,
+                };
+                let host = get_host(&job.get_url());
+                let crawl_delay = rules
+                    .robots
+                    .as_ref()
+                    .and_then(|robots| robots.get_cached(&job.get_url()))
+                    .and_then(|robots| robots.get_crawl_delay());
+                scheduler.start(&host, now, crawl_delay);
+                job.set_revision(
+                    history
+                        .as_mut()
+                        .and_then(|history| history.get(&job.get_url())),
+                );
+                in_flight.insert(job.get_url(), job.clone());
+                job_sender.send(job).unwrap();
             }
 
-            for handler in handlers {
-                if let Some(jobs) = handler.join().unwrap() {
-                    for job in jobs {
-                        self.queue.enqueue(job);
+            // once a limit is hit we only wait for the in-flight jobs to finish
+            let done = self.queue.is_empty() && retrying.is_empty();
+            if in_flight.is_empty() && (stop_reason.is_some() || done) {
+                break;
+            }
+
+            if let Some((path, interval)) = &self.checkpoint {
+                if last_checkpoint.elapsed() >= *interval {
+                    state.report.elapsed = elapsed_before + started.elapsed();
+                    state.report.contacts = contacts.iter().cloned().collect();
+                    let jobs = in_flight
+                        .values()
+                        .chain(retrying.iter().map(|(_, job)| job));
+                    if let Err(err) = state.write(path, &mut self.queue, jobs) {
+                        loge!(format!("Failed to write checkpoint: {}", err));
+                    }
+                    if let Some(Err(err)) = history.as_mut().map(|history| history.write()) {
+                        loge!(format!("Failed to write history: {}", err));
                     }
+                    last_checkpoint = Instant::now();
                 }
             }
 
-            if self.queue.is_empty() {
-                break;
+            // wait for a worker to finish, for a host to become ready again or for a retry
+            let next_retry_at = retrying.iter().map(|(retry_at, _)| *retry_at).min();
+            let host_ready_at = if self.queue.is_empty() {
+                None
+            } else {
+                scheduler.next_ready_at(now)
+            };
+            let next_ready_at = match (host_ready_at, next_retry_at) {
+                (Some(ready_at), Some(retry_at)) => Some(ready_at.min(retry_at)),
+                (ready_at, retry_at) => ready_at.or(retry_at),
+            }
+            .filter(|_| stop_reason.is_none());
+            let message = match next_ready_at {
+                Some(ready_at) => {
+                    let timeout = ready_at.saturating_duration_since(Instant::now());
+                    match result_receiver.recv_timeout(timeout) {
+                        Ok(message) => Some(message),
+                        Err(RecvTimeoutError::Timeout) => continue,
+                        Err(RecvTimeoutError::Disconnected) => None,
+                    }
+                }
+                None => result_receiver.recv().ok(),
+            };
+            let result = match message {
+                Some(WorkerMessage::Redirect(job_url, url, reply_sender)) => {
+                    let claimed = claims.entry(job_url).or_default();
+                    let unseen = claimed.contains(&url)
+                        || Job::new(self.fetcher.clone(), url.clone(), 0, &self.scheme_policy)
+                            .is_some_and(|job| self.queue.mark_seen(&job));
+                    if unseen {
+                        claimed.push(url);
+                    }
+                    reply_sender.send(unseen).unwrap_or(());
+                    continue;
+                }
+                Some(WorkerMessage::Done(result)) => result,
+                // the job of a dead worker would be waited for forever. Dropping the receiver
+                // unblocks the workers which wait for an answer to a redirect
+                Some(WorkerMessage::Panicked) | None => {
+                    drop(job_sender);
+                    drop(result_receiver);
+                    join_workers(handlers)?;
+                    return Err(WorkerError("A worker stopped unexpectedly".to_string()));
+                }
+            };
+
+            // feed newly discovered jobs back as soon as any worker is done
+            let job = in_flight.remove(&result.url);
+            scheduler.finish(&result.host, Instant::now());
+            if let Some(robots) = &rules.robots {
+                for robots_url in robots.take_fetched() {
+                    let crawl_delay = robots
+                        .get_cached(&robots_url)
+                        .and_then(|robots| robots.get_crawl_delay());
+                    scheduler.record(&get_host(&robots_url), Instant::now(), crawl_delay);
+                }
+            }
+            let report = &mut state.report;
+            // the job goes back into the queue later on
+            let retry = match (&result.error, job) {
+                (Some(err), Some(mut job)) if retry::is_transient(err) => self
+                    .retry_policy
+                    .get_delay(job.get_retries(), result.retry_after)
+                    .map(|delay| {
+                        job.set_retries(job.get_retries() + 1);
+                        (Instant::now() + delay, job)
+                    }),
+                _ => None,
+            };
+            if let Some((retry_at, job)) = retry {
+                log!(format!("Retrying {} ({})", &result.url, job.get_retries()));
+                retrying.push((retry_at, job));
+                report.retries += 1;
+                continue;
+            }
+            claims.remove(&result.url);
+            if let Some(len) = result.bytes {
+                report.fetched += 1;
+                report.bytes += len;
+            }
+            if result.truncated {
+                report.truncated += 1;
+            }
+            if result.unchanged {
+                report.unchanged += 1;
+            } else if result.revalidated && result.bytes.is_some() {
+                report.changed += 1;
+            }
+            // pages which couldn't be persisted are fetched again next time and pages which are
+            // gone are forgotten
+            let gone = result.error.as_ref().is_some_and(history::is_gone);
+            if let Some(history) = history.as_mut() {
+                if gone {
+                    history.remove(&result.url);
+                } else if result.bytes.is_some() && result.error.is_none() {
+                    match &result.revision {
+                        Some(revision) => {
+                            if let Err(err) = history.insert(result.url.clone(), revision) {
+                                loge!(format!("Failed to write history: {}", err));
+                            }
+                        }
+                        None => history.remove(&result.url),
+                    }
+                }
+            }
+            if result.persisted {
+                report.persisted += 1;
+            }
+            for (reason, count) in result.skipped {
+                *report.skipped.entry(reason).or_insert(0) += count;
+            }
+            if let Some(err) = result.error {
+                *report.failed.entry(err.get_kind().to_string()).or_insert(0) += 1;
+                report.failed_urls.push(result.url);
+            }
+            contacts.extend(result.contacts.unwrap_or_default());
+            report.elapsed = elapsed_before + started.elapsed();
+            if stop_reason.is_none() && self.observer.should_stop(report) {
+                stop_reason = Some(StopReason::Stopped);
+            }
+            if stop_reason.is_none() {
+                if let Some(jobs) = result.jobs {
+                    for job in jobs {
+                        let (url, depth) = (job.get_url(), job.get_depth());
+                        if self.queue.enqueue(job) {
+                            self.observer.on_enqueue(&url, depth);
+                        }
+                    }
+                }
             }
         }
 
-        Ok(())
+        // dropping the sender shuts the workers down
+        drop(job_sender);
+        join_workers(handlers)?;
+
+        // the final checkpoint keeps the jobs which weren't crawled because a limit was hit
+        state.report.elapsed = elapsed_before + started.elapsed();
+        state.report.contacts = contacts.into_iter().collect();
+        if let Some((path, _)) = &self.checkpoint {
+            let jobs = retrying.iter().map(|(_, job)| job);
+            state.write(path, &mut self.queue, jobs)?;
+        }
+        if let Some(history) = history.as_mut() {
+            history.write()?;
+        }
+
+        state.report.stop_reason = stop_reason.unwrap_or(StopReason::Finished);
+        self.observer.on_finish(&state.report);
+        Ok(state.report)
+    }
+
+    fn exceeded_limit(&self, pages: usize, bytes: usize, elapsed: Duration) -> Option<StopReason> {
+        if self.max_pages.is_some_and(|max_pages| pages >= max_pages) {
+            return Some(StopReason::MaxPages);
+        }
+        if self.max_bytes.is_some_and(|max_bytes| bytes >= max_bytes) {
+            return Some(StopReason::MaxBytes);
+        }
+        if self
+            .max_duration
+            .is_some_and(|max_duration| elapsed >= max_duration)
+        {
+            return Some(StopReason::MaxDuration);
+        }
+        None
     }
 }
 
+// waits for the workers to shut down and turns the panic of any of them into an error
+fn join_workers(handlers: Vec<thread::JoinHandle<()>>) -> shared::Result<()> {
+    let mut result = Ok(());
+    for handler in handlers {
+        if let Err(payload) = handler.join() {
+            let message = payload
+                .downcast_ref::<&str>()
+                .map(|message| message.to_string())
+                .or_else(|| payload.downcast_ref::<String>().cloned())
+                .unwrap_or_default();
+            result = Err(WorkerError(format!("A worker panicked: {}", message)));
+        }
+    }
+    result
+}
+
+fn get_host(url: &Url) -> String {
+    url.host_str().unwrap_or("").to_string()
+}
+
+// removes the jobs which are due for a retry
+fn drain_due<T>(retrying: &mut Vec<(Instant, T)>, now: Instant) -> Vec<(Instant, T)> {
+    let (due, later) = mem::take(retrying)
+        .into_iter()
+        .partition(|(retry_at, _)| *retry_at <= now);
+    *retrying = later;
+    due
+}
+
 fn crawl<A, B>(
     persister: Arc<A>,
     fetcher: Arc<B>,
     job: Job<B>,
-) -> shared::Result<CrawlingResult<A, B>>
+    rules: &Rules,
+    observer: &dyn Observe,
+    claim: &dyn Fn(&Url) -> bool,
+) -> shared::Result<Crawled<A, B>>
 where
     A: Persist,
     B: Fetch,
 {
     let url = job.get_url();
+    // found links were checked against `robots.txt` already, the starting URL wasn't
+    if let Some(robots) = rules.robots.as_ref().filter(|_| job.get_depth() == 0) {
+        if !robots.allows(fetcher.as_ref(), &url) {
+            return Ok(Crawled::Skipped(SkipReason::Robots));
+        }
+    }
     log!(format!("GET {}", &url));
-    if let Ok((content_type, content)) = job.fetch() {
-        let crawling = Crawling::new(persister, job.get_url(), content_type.as_str(), content);
-        if let Some(urls) = crawling.find_urls() {
-            let jobs_arr = Vec::<Job<B>>::with_capacity(urls.len());
-            let jobs = urls.into_iter().fold(jobs_arr, |mut accum, url| {
-                if let Some(job) = Job::new(fetcher.clone(), url) {
-                    accum.push(job);
-                }
-                accum
-            });
-            return Ok(CrawlingResult {
-                crawling,
-                jobs: Some(jobs),
-            });
+    observer.on_fetch_start(&url);
+    let mut response = job.fetch()?;
+    let depth = job.get_depth() + 1;
+    // the links of an unchanged page are followed as if it was fetched again
+    if let Some(revision) = job.get_revision().filter(|_| response.status == 304) {
+        observer.on_fetch_done(&url, Some(response.status), 0, response.duration);
+        let nofollow = rules.respect_nofollow && revision.nofollow;
+        let mut skipped = BTreeMap::new();
+        let links = revision.links.clone();
+        let jobs = to_jobs(&fetcher, links, depth, nofollow, rules, &mut skipped);
+        return Ok(Crawled::Unchanged(jobs, skipped));
+    }
+    // redirects which the fetcher followed on its own can only be checked afterwards
+    if !response.redirects.is_empty() {
+        let final_url = rules.canonicalizer.canonicalize(&response.url);
+        if let Err(reason) = check_redirect(&fetcher, &final_url, rules, claim) {
+            return Ok(Crawled::Skipped(reason));
+        }
+    }
+    let mut redirects = mem::take(&mut response.redirects);
+    let mut duration = response.duration;
+    while let Some(location) = response.get_location() {
+        if redirects.len() >= rules.max_redirects {
+            return Err(RequestError(format!(
+                "Too many redirects for URL \"{}\"",
+                url
+            )));
+        }
+        let location = rules.canonicalizer.canonicalize(&location);
+        if let Err(reason) = check_redirect(&fetcher, &location, rules, claim) {
+            return Ok(Crawled::Skipped(reason));
+        }
+        log!(format!("GET {}", &location));
+        redirects.push(response.url);
+        response = fetcher.fetch_response(&location)?;
+        redirects.append(&mut response.redirects);
+        duration += response.duration;
+    }
+    response.redirects = redirects;
+    response.duration = duration;
+    let (status, len) = (response.status, response.content.len());
+    observer.on_fetch_done(&url, Some(status), len, response.duration);
+    if !response.is_success() {
+        return Ok(Crawled::Unsuccessful(Box::new(response)));
+    }
+
+    // links are resolved against the URL the page was fetched from in the end
+    let mut crawling = Crawling::from_response(persister, response);
+    crawling.set_scheme_policy(rules.scheme_policy.clone());
+    let mut skipped = BTreeMap::new();
+    let rejected_links = crawling.count_rejected_links();
+    if rejected_links > 0 {
+        skipped.insert(SkipReason::Scheme, rejected_links);
+    }
+    let contacts = if rules.scheme_policy.collect_contacts {
+        crawling.find_contacts()
+    } else {
+        None
+    };
+
+    let directives = crawling.get_directives();
+    let nofollow = rules.respect_nofollow && directives.nofollow;
+    // NOTE: pages which were redirected to are always fetched again since the conditional
+    // request is only sent for the requested URL
+    let mut revision = Some(crawling.get_response())
+        .filter(|response| response.redirects.is_empty())
+        .and_then(|response| Revision::from_response(response, directives.nofollow));
+    let mut jobs = None;
+    if let Some(links) = crawling.find_links() {
+        if let Some(revision) = revision.as_mut() {
+            revision.links = links.clone();
+        }
+        jobs = Some(to_jobs(
+            &fetcher,
+            links,
+            depth,
+            nofollow,
+            rules,
+            &mut skipped,
+        ));
+    }
+    Ok(Crawled::Page(Box::new(CrawlingResult {
+        crawling,
+        jobs,
+        contacts,
+        skipped,
+        revision,
+    })))
+}
+
+// the target of a redirect has to pass the same checks as a found link. It's claimed in the seen
+// set so that a page which is reachable through several URLs is only crawled once
+fn check_redirect<B>(
+    fetcher: &Arc<B>,
+    url: &Url,
+    rules: &Rules,
+    claim: &dyn Fn(&Url) -> bool,
+) -> Result<(), SkipReason>
+where
+    B: Fetch,
+{
+    if !rules.scope.allows(&rules.seed, url) {
+        return Err(SkipReason::Scope);
+    }
+    if !rules.filter.allows(url) {
+        return Err(SkipReason::Filter);
+    }
+    if let Some(robots) = &rules.robots {
+        if !robots.allows(fetcher.as_ref(), url) {
+            return Err(SkipReason::Robots);
+        }
+    }
+    if !rules.scheme_policy.admits(url) {
+        return Err(SkipReason::Scheme);
+    }
+    if !claim(url) {
+        return Err(SkipReason::Duplicate);
+    }
+    Ok(())
+}
+
+// turns a link found on a page into a new job or tells why it was skipped. The cheap checks come
+// first so that `robots.txt` is only fetched for links which would be crawled otherwise
+fn to_jobs<B>(
+    fetcher: &Arc<B>,
+    links: Vec<Link>,
+    depth: usize,
+    nofollow: bool,
+    rules: &Rules,
+    skipped: &mut BTreeMap<SkipReason, usize>,
+) -> Vec<Job<B>>
+where
+    B: Fetch,
+{
+    let mut jobs = Vec::with_capacity(links.len());
+    for link in links {
+        match to_job(fetcher, link, depth, nofollow, rules) {
+            Ok(job) => jobs.push(job),
+            Err(reason) => *skipped.entry(reason).or_insert(0) += 1,
+        }
+    }
+    jobs
+}
+
+fn to_job<B>(
+    fetcher: &Arc<B>,
+    link: Link,
+    depth: usize,
+    nofollow: bool,
+    rules: &Rules,
+) -> Result<Job<B>, SkipReason>
+where
+    B: Fetch,
+{
+    if !rules.link_kinds.contains(&link.kind) {
+        return Err(SkipReason::Kind);
+    }
+    if nofollow || (rules.respect_nofollow && link.nofollow) {
+        return Err(SkipReason::Nofollow);
+    }
+    // jobs beyond the maximum depth are never created
+    if rules.max_depth.is_some_and(|max_depth| depth > max_depth) {
+        return Err(SkipReason::Depth);
+    }
+    let url = rules.canonicalizer.canonicalize(&link.url);
+    if !rules.scope.allows(&rules.seed, &url) {
+        return Err(SkipReason::Scope);
+    }
+    if !rules.filter.allows(&url) {
+        return Err(SkipReason::Filter);
+    }
+    if let Some(robots) = &rules.robots {
+        if !robots.allows(fetcher.as_ref(), &url) {
+            return Err(SkipReason::Robots);
         }
-        return Ok(CrawlingResult {
-            crawling,
-            jobs: None,
-        });
     }
-    Err(FetchingError(format!("Failed to GET {}", &url)))
+    // links of a page from the history were admitted by the scheme policy of a previous crawl
+    let mut job =
+        Job::new(fetcher.clone(), url, depth, &rules.scheme_policy).ok_or(SkipReason::Scheme)?;
+    job.set_anchor_text(link.text);
+    Ok(job)
 }
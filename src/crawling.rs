@@ -1,16 +1,15 @@
+use crate::charset;
 use crate::error::CrawlerError::PersistingError;
+use crate::html::{parse_tags, Tag};
+use crate::response::Response;
 use crate::shared::{self, hash};
 use crate::traits::Persist;
-use regex::Regex;
 use reqwest::Url;
+use std::fmt::{self, Display, Formatter};
 use std::str::FromStr;
 use std::sync::Arc;
 
-lazy_static! {
-    static ref LINK_REGEX: Regex =
-        Regex::new(r#"\s*(?i)href\s*=\s*("([^"]*)"|'[^']*'|([^'">\s]+))"#)
-            .unwrap_or_else(|_| panic!("Error parsing Regex"));
-}
+static DEFAULT_SCHEMES: [&str; 2] = ["http", "https"];
 
 #[derive(PartialEq, Debug)]
 pub enum Kind {
@@ -19,14 +18,182 @@ pub enum Kind {
     Unknown,
 }
 
+// the directives of a page's `<meta name="robots">` tag
+#[derive(PartialEq, Debug, Default, Clone, Copy)]
+pub struct Directives {
+    pub noindex: bool,
+    pub nofollow: bool,
+}
+
+// the element a link was found in
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy)]
+pub enum LinkKind {
+    // `<a href>`
+    Anchor,
+    // `<area href>`
+    Area,
+    // `<link href>`
+    Link,
+    // `<img src>`, `<img srcset>` and `<source srcset>`
+    Image,
+    // `<iframe src>` and `<frame src>`
+    Iframe,
+    // `<form action>` of forms which are submitted via `GET`
+    Form,
+    // `<meta http-equiv="refresh">`
+    MetaRefresh,
+}
+
+impl LinkKind {
+    // the kinds of links which are followed by default
+    pub fn defaults() -> Vec<LinkKind> {
+        vec![LinkKind::Anchor, LinkKind::Area, LinkKind::Link]
+    }
+}
+
+impl Display for LinkKind {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let kind = match self {
+            LinkKind::Anchor => "a",
+            LinkKind::Area => "area",
+            LinkKind::Link => "link",
+            LinkKind::Image => "img",
+            LinkKind::Iframe => "iframe",
+            LinkKind::Form => "form",
+            LinkKind::MetaRefresh => "refresh",
+        };
+        write!(f, "{}", kind)
+    }
+}
+
+impl FromStr for LinkKind {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "a" => Ok(LinkKind::Anchor),
+            "area" => Ok(LinkKind::Area),
+            "link" => Ok(LinkKind::Link),
+            "img" => Ok(LinkKind::Image),
+            "iframe" => Ok(LinkKind::Iframe),
+            "form" => Ok(LinkKind::Form),
+            "refresh" => Ok(LinkKind::MetaRefresh),
+            _ => Err(format!("Unknown link kind \"{}\"", value)),
+        }
+    }
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct Link {
+    pub url: Url,
+    pub kind: LinkKind,
+    // `true` if the link has a `rel="nofollow"` attribute
+    pub nofollow: bool,
+    // the anchor text of `<a>` links
+    pub text: Option<String>,
+}
+
+// e.g. `a nofollow http://example.com/ Example`. The anchor text comes last and is written on a
+// single line
+impl Display for Link {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let follow = if self.nofollow { "nofollow" } else { "follow" };
+        write!(f, "{} {} {}", self.kind, follow, self.url)?;
+        if let Some(text) = &self.text {
+            let text: Vec<&str> = text.split_whitespace().collect();
+            write!(f, " {}", text.join(" "))?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Link {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid link \"{}\"", value);
+        let mut parts = value.splitn(4, ' ');
+        let kind = parts.next().ok_or_else(invalid)?.parse()?;
+        let nofollow = match parts.next() {
+            Some("follow") => false,
+            Some("nofollow") => true,
+            _ => return Err(invalid()),
+        };
+        let url = parts.next().and_then(|url| Url::parse(url).ok());
+        Ok(Link {
+            url: url.ok_or_else(invalid)?,
+            kind,
+            nofollow,
+            text: parts.next().map(|text| text.to_string()),
+        })
+    }
+}
+
+// decides which links are returned by `find_links` and which URLs can become jobs based on their
+// scheme
+#[derive(PartialEq, Debug, Clone)]
+pub struct SchemePolicy {
+    pub schemes: Vec<String>,
+    // collect `mailto:` and `tel:` links as contacts rather than discarding them
+    pub collect_contacts: bool,
+}
+
+impl Default for SchemePolicy {
+    fn default() -> Self {
+        SchemePolicy {
+            schemes: DEFAULT_SCHEMES.iter().map(|s| s.to_string()).collect(),
+            collect_contacts: false,
+        }
+    }
+}
+
+impl SchemePolicy {
+    pub fn admits(&self, url: &Url) -> bool {
+        self.schemes.iter().any(|scheme| scheme == url.scheme())
+    }
+}
+
+#[derive(PartialEq, Eq, Hash, PartialOrd, Ord, Debug, Clone)]
+pub enum Contact {
+    Email(String),
+    Phone(String),
+}
+
+impl Display for Contact {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Contact::Email(address) => write!(f, "mailto:{}", address),
+            Contact::Phone(number) => write!(f, "tel:{}", number),
+        }
+    }
+}
+
+impl FromStr for Contact {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        if let Some(address) = value.strip_prefix("mailto:") {
+            return Ok(Contact::Email(address.to_string()));
+        }
+        if let Some(number) = value.strip_prefix("tel:") {
+            return Ok(Contact::Phone(number.to_string()));
+        }
+        Err(format!("Unknown contact \"{}\"", value))
+    }
+}
+
 pub struct Crawling<T>
 where
     T: Persist,
 {
     persister: Arc<T>,
-    url: Url,
-    content: Vec<u8>,
+    response: Response,
     kind: Kind,
+    tags: Vec<Tag>,
+    // all links of the page, whatever their scheme. They're extracted once since the scheme policy
+    // only filters them
+    links: Vec<Link>,
+    scheme_policy: SchemePolicy,
 }
 
 impl<T> Crawling<T>
@@ -34,36 +201,187 @@ where
     T: Persist,
 {
     pub fn new(persister: Arc<T>, url: Url, content_type: &str, content: Vec<u8>) -> Self {
-        let kind = Self::identify_kind(content_type);
-        Crawling {
+        Self::from_response(persister, Response::new(url, content_type, content))
+    }
+
+    // relative links are resolved against the URL the response was fetched from. HTML pages are
+    // decoded before they're parsed and their encoding is kept in the response for the persister
+    pub fn from_response(persister: Arc<T>, mut response: Response) -> Self {
+        let kind = Self::identify_kind(&response.content_type);
+        let tags = if kind == Kind::Html {
+            // fetchers which know the encoding better can set it beforehand
+            let encoding = response
+                .encoding
+                .unwrap_or_else(|| charset::detect(&response.content_type, &response.content));
+            response.encoding = Some(encoding);
+            parse_tags(&charset::decode(&response.content, encoding))
+        } else {
+            vec![]
+        };
+        let mut crawling = Crawling {
             persister,
-            url,
-            content,
+            response,
             kind,
-        }
+            tags,
+            links: vec![],
+            scheme_policy: SchemePolicy::default(),
+        };
+        crawling.links = crawling.find_all_links();
+        crawling
     }
 
+    pub fn set_scheme_policy(&mut self, scheme_policy: SchemePolicy) {
+        self.scheme_policy = scheme_policy;
+    }
+
+    // only returns the URLs of the kinds of links which are followed by default
     pub fn find_urls(&self) -> Option<Vec<Url>> {
-        if self.kind == Kind::Html {
-            let content = String::from_utf8_lossy(&self.content);
-            let mut links: Vec<Url> = vec![];
-            for cap in LINK_REGEX.captures_iter(&content) {
-                if cap.get(2).is_some() {
-                    let mut url = String::from(&cap[2]);
-                    if !url.starts_with("http") || !url.starts_with("https") {
-                        url = self.url.join(&url).unwrap().to_string();
+        let kinds = LinkKind::defaults();
+        let urls: Vec<Url> = self
+            .find_links()?
+            .into_iter()
+            .filter(|link| kinds.contains(&link.kind))
+            .map(|link| link.url)
+            .collect();
+        if !urls.is_empty() {
+            return Some(urls);
+        }
+        None
+    }
+
+    // only returns the links whose scheme is admitted by the scheme policy
+    pub fn find_links(&self) -> Option<Vec<Link>> {
+        let links: Vec<Link> = self
+            .links
+            .iter()
+            .filter(|link| self.scheme_policy.admits(&link.url))
+            .cloned()
+            .collect();
+        if !links.is_empty() {
+            return Some(links);
+        }
+        None
+    }
+
+    // the number of links which were rejected because of their scheme
+    pub fn count_rejected_links(&self) -> usize {
+        self.links
+            .iter()
+            .filter(|link| !self.scheme_policy.admits(&link.url))
+            .count()
+    }
+
+    // returns the addresses of all `mailto:` and `tel:` links
+    pub fn find_contacts(&self) -> Option<Vec<Contact>> {
+        let contacts: Vec<Contact> = self
+            .links
+            .iter()
+            .filter_map(|link| {
+                // `mailto:` links can carry a subject or body in their query
+                let address = link.url.path();
+                if address.is_empty() {
+                    return None;
+                }
+                match link.url.scheme() {
+                    "mailto" => Some(Contact::Email(address.to_string())),
+                    "tel" => Some(Contact::Phone(address.to_string())),
+                    _ => None,
+                }
+            })
+            .collect();
+        if !contacts.is_empty() {
+            return Some(contacts);
+        }
+        None
+    }
+
+    fn find_all_links(&self) -> Vec<Link> {
+        let base = self.get_base();
+        let mut links: Vec<Link> = vec![];
+        for tag in self.tags.iter() {
+            let nofollow = tag.get_attribute("rel").is_some_and(|rel| {
+                rel.split_whitespace()
+                    .any(|rel| rel.eq_ignore_ascii_case("nofollow"))
+            });
+            let mut push = |kind: LinkKind, value: Option<&str>| {
+                let value = value.map(|value| value.trim()).unwrap_or("");
+                if value.is_empty() {
+                    return;
+                }
+                if let Ok(url) = base.join(value) {
+                    links.push(Link {
+                        url,
+                        kind,
+                        nofollow,
+                        text: tag.text.clone(),
+                    });
+                }
+            };
+            match tag.name.as_str() {
+                "a" => push(LinkKind::Anchor, tag.get_attribute("href")),
+                "area" => push(LinkKind::Area, tag.get_attribute("href")),
+                "link" => push(LinkKind::Link, tag.get_attribute("href")),
+                "img" | "source" => {
+                    push(LinkKind::Image, tag.get_attribute("src"));
+                    for candidate in parse_srcset(tag.get_attribute("srcset").unwrap_or("")) {
+                        push(LinkKind::Image, Some(candidate));
                     }
-                    let parsing = Url::from_str(&url);
-                    if let Ok(parsing) = parsing {
-                        links.push(parsing);
+                }
+                "iframe" | "frame" => push(LinkKind::Iframe, tag.get_attribute("src")),
+                "form" => {
+                    let method = tag.get_attribute("method").unwrap_or("get");
+                    if method.trim().eq_ignore_ascii_case("get") {
+                        push(LinkKind::Form, tag.get_attribute("action"));
                     }
                 }
+                "meta" => {
+                    let is_refresh = tag
+                        .get_attribute("http-equiv")
+                        .is_some_and(|value| value.eq_ignore_ascii_case("refresh"));
+                    if is_refresh {
+                        let content = tag.get_attribute("content").unwrap_or("");
+                        push(LinkKind::MetaRefresh, parse_refresh(content));
+                    }
+                }
+                _ => {}
             }
-            if !links.is_empty() {
-                return Some(links);
+        }
+        links
+    }
+
+    // relative links are resolved against the first `<base href>` or the URL of the page
+    pub fn get_base(&self) -> Url {
+        self.tags
+            .iter()
+            .filter(|tag| tag.name == "base")
+            .find_map(|tag| tag.get_attribute("href"))
+            .and_then(|href| self.response.url.join(href.trim()).ok())
+            .unwrap_or_else(|| self.response.url.clone())
+    }
+
+    pub fn get_directives(&self) -> Directives {
+        let mut directives = Directives::default();
+        let robots = self.tags.iter().filter(|tag| {
+            tag.name == "meta"
+                && tag
+                    .get_attribute("name")
+                    .is_some_and(|name| name.eq_ignore_ascii_case("robots"))
+        });
+        for tag in robots {
+            let content = tag.get_attribute("content").unwrap_or("");
+            for directive in content.split(',').map(|d| d.trim().to_ascii_lowercase()) {
+                match directive.as_str() {
+                    "noindex" => directives.noindex = true,
+                    "nofollow" => directives.nofollow = true,
+                    "none" => {
+                        directives.noindex = true;
+                        directives.nofollow = true;
+                    }
+                    _ => {}
+                }
             }
         }
-        None
+        directives
     }
 
     pub fn identify_kind(content_type: &str) -> Kind {
@@ -81,18 +399,38 @@ where
         if has_domain && has_file_extension {
             let domain_prefix = self.get_domain().unwrap();
             let file_extension = self.get_file_extension().unwrap();
-            let url = self.url.as_str();
-            let content = self.content.as_slice();
-            let hash = hash(&content);
+            let hash = hash(&self.response.content.as_slice());
             let formatted_str = format!("{}-{}{}", domain_prefix, hash, file_extension);
             let id = formatted_str.as_str();
-            return self.persister.persist(id, url, content);
+            if !self.should_persist() {
+                return Ok(0);
+            }
+            return self.persister.persist_response(id, &self.response);
         }
         Err(PersistingError("Failed to write Crawling".to_string()))
     }
 
+    // `false` if the persister skips the page, e.g. because of a `noindex` directive
+    pub fn should_persist(&self) -> bool {
+        self.persister
+            .should_persist(self.response.url.as_str(), &self.get_directives())
+    }
+
+    pub fn get_content(&self) -> &[u8] {
+        &self.response.content
+    }
+
+    pub fn get_response(&self) -> &Response {
+        &self.response
+    }
+
+    // `true` if only the start of the content was kept because it exceeded the size limit
+    pub fn is_truncated(&self) -> bool {
+        self.response.truncated
+    }
+
     pub fn get_domain(&self) -> Option<&str> {
-        self.url.domain()
+        self.response.url.domain()
     }
 
     pub fn get_file_extension(&self) -> Option<&str> {
@@ -104,37 +442,72 @@ where
     }
 }
 
+// returns the URLs of all image candidates, e.g. `a.png 1x, b.png 2x`. URLs can contain commas, so
+// a candidate only ends at a comma after its URL, e.g. `/w_100,h_50/a.png 1x`
+fn parse_srcset(srcset: &str) -> Vec<&str> {
+    let mut urls = vec![];
+    let mut rest = srcset;
+    loop {
+        rest = rest.trim_start_matches(|c: char| c.is_ascii_whitespace() || c == ',');
+        if rest.is_empty() {
+            return urls;
+        }
+        let end = rest
+            .find(|c: char| c.is_ascii_whitespace())
+            .unwrap_or(rest.len());
+        let (candidate, descriptors) = rest.split_at(end);
+        rest = descriptors;
+        // a URL which ends with a comma has no descriptors
+        let url = candidate.trim_end_matches(',');
+        if url.len() == candidate.len() {
+            rest = rest.find(',').map_or("", |idx| &rest[idx + 1..]);
+        }
+        urls.push(url);
+    }
+}
+
+// returns the URL of a refresh, e.g. `5; url=/foo` or `5; /foo`
+fn parse_refresh(content: &str) -> Option<&str> {
+    let (_, rest) = content.split_once(';')?;
+    let rest = rest.trim_start();
+    // the `url=` is optional
+    let url = rest
+        .get(..3)
+        .filter(|prefix| prefix.eq_ignore_ascii_case("url"))
+        .and_then(|_| rest[3..].trim_start().strip_prefix('='))
+        .unwrap_or(rest);
+    Some(url.trim().trim_matches(|c| c == '"' || c == '\'')).filter(|url| !url.is_empty())
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::crawling::{Crawling, Kind};
+    use crate::crawling::{
+        parse_refresh, parse_srcset, Contact, Crawling, Directives, Kind, Link, LinkKind,
+        SchemePolicy,
+    };
     use crate::shared;
     use crate::traits::Persist;
     use reqwest::Url;
-    use std::cell::RefCell;
     use std::collections::HashMap;
     use std::str::FromStr;
-    use std::sync::Arc;
+    use std::sync::{Arc, Mutex};
 
     struct MockPersister {
-        dest: RefCell<HashMap<String, String>>,
+        dest: Mutex<HashMap<String, String>>,
     }
     impl Default for MockPersister {
         fn default() -> Self {
             MockPersister {
-                dest: RefCell::new(HashMap::<String, String>::new()),
+                dest: Mutex::new(HashMap::<String, String>::new()),
             }
         }
     }
     impl Persist for MockPersister {
         fn persist(&self, id: &str, url: &str, content: &[u8]) -> shared::Result<usize> {
-            let mut dest = self.dest.borrow_mut();
+            let mut dest = self.dest.lock().unwrap();
             dest.insert(
                 id.to_string(),
-                format!(
-                    "{} --> {}",
-                    url,
-                    String::from_utf8_lossy(content).to_string()
-                ),
+                format!("{} --> {}", url, String::from_utf8_lossy(content)),
             );
             Ok(id.len() + content.len())
         }
@@ -171,10 +544,52 @@ mod tests {
             Some(vec![
                 get_url("http://example.com/news"),
                 get_url("http://example.com/home?foo=bar&baz=qux#foo"),
-                get_url("https://jdoe.com"),
-                get_url("mailto:jdoe@example.com")
+                get_url("https://jdoe.com")
+            ])
+        );
+    }
+
+    #[test]
+    fn html_crawling_find_urls_schemes() {
+        let url = get_url("http://example.com");
+        let persister = get_mock_persister();
+
+        let mut crawling = Crawling::new(
+            persister,
+            url,
+            "text/html",
+            b"<html><a href=\"/foo\">Foo</a><a href=\"ftp://example.com/file\">File</a>\
+            <a href=\"javascript:void(0)\">Click</a><a href=\"data:text/plain,foo\">Data</a>\
+            <a href=\"mailto:jdoe@example.com?subject=Hi\">Mail</a><a href=\"tel:+1-555-0100\">\
+            Call</a></html>"
+                .to_vec(),
+        );
+
+        assert_eq!(
+            crawling.find_urls(),
+            Some(vec![get_url("http://example.com/foo")])
+        );
+        assert_eq!(crawling.count_rejected_links(), 5);
+        assert_eq!(
+            crawling.find_contacts(),
+            Some(vec![
+                Contact::Email("jdoe@example.com".to_string()),
+                Contact::Phone("+1-555-0100".to_string())
             ])
         );
+
+        crawling.set_scheme_policy(SchemePolicy {
+            schemes: vec!["http".to_string(), "ftp".to_string()],
+            collect_contacts: false,
+        });
+        assert_eq!(
+            crawling.find_urls(),
+            Some(vec![
+                get_url("http://example.com/foo"),
+                get_url("ftp://example.com/file")
+            ])
+        );
+        assert_eq!(crawling.count_rejected_links(), 4);
     }
 
     #[test]
@@ -202,11 +617,219 @@ mod tests {
             persister,
             url,
             "text/html",
-            b"<html><a href='http://google.com'></a></html>".to_vec(),
+            b"<html><a href='http://google.com'></a><a href=/foo?a=1&amp;b=2>Foo</a></html>"
+                .to_vec(),
         );
 
         assert_eq!(crawling.kind, Kind::Html);
-        assert_eq!(crawling.find_urls(), None);
+        assert_eq!(
+            crawling.find_urls(),
+            Some(vec![
+                get_url("http://google.com"),
+                get_url("http://example.com/foo?a=1&b=2")
+            ])
+        );
+    }
+
+    #[test]
+    fn html_crawling_find_urls_ignores_comments_and_scripts() {
+        let url = get_url("http://example.com");
+        let persister = get_mock_persister();
+
+        let crawling = Crawling::new(
+            persister,
+            url,
+            "text/html",
+            b"<html><!-- <a href=\"/comment\"></a> -->\
+            <script>document.write('<a href=\"/script\"></a>');</script>\
+            <style>a[href=\"/style\"] { color: red; }</style>\
+            <a href=\"/page\">Page</a></html>"
+                .to_vec(),
+        );
+
+        assert_eq!(
+            crawling.find_urls(),
+            Some(vec![get_url("http://example.com/page")])
+        );
+    }
+
+    #[test]
+    fn html_crawling_find_urls_base() {
+        let url = get_url("http://example.com/foo/bar");
+        let persister = get_mock_persister();
+
+        let crawling = Crawling::new(
+            persister,
+            url,
+            "text/html",
+            b"<html><head><base href=\"/docs/\"><base href=\"/ignored/\"></head>\
+            <a href=\"intro\">Intro</a><a href=\"../about\">About</a></html>"
+                .to_vec(),
+        );
+
+        assert_eq!(crawling.get_base(), get_url("http://example.com/docs/"));
+        assert_eq!(
+            crawling.find_urls(),
+            Some(vec![
+                get_url("http://example.com/docs/intro"),
+                get_url("http://example.com/about")
+            ])
+        );
+    }
+
+    #[test]
+    fn html_crawling_find_links_nofollow() {
+        let url = get_url("http://example.com");
+        let persister = get_mock_persister();
+
+        let crawling = Crawling::new(
+            persister,
+            url,
+            "text/html",
+            b"<html><a href=\"/foo\">Foo</a><a href=\"/bar\" rel=\"external NoFollow\">Bar</a>\
+            </html>"
+                .to_vec(),
+        );
+
+        assert_eq!(
+            crawling.find_links(),
+            Some(vec![
+                Link {
+                    url: get_url("http://example.com/foo"),
+                    kind: LinkKind::Anchor,
+                    nofollow: false,
+                    text: Some("Foo".to_string())
+                },
+                Link {
+                    url: get_url("http://example.com/bar"),
+                    kind: LinkKind::Anchor,
+                    nofollow: true,
+                    text: Some("Bar".to_string())
+                }
+            ])
+        );
+    }
+
+    #[test]
+    fn html_crawling_find_links_kinds() {
+        let url = get_url("http://example.com");
+        let persister = get_mock_persister();
+
+        let crawling = Crawling::new(
+            persister,
+            url,
+            "text/html",
+            b"<html><head><link rel=\"stylesheet\" href=\"/style.css\">\
+            <meta http-equiv=\"Refresh\" content=\"5; URL='/refresh'\"></head>\
+            <img src=\"/a.png\" srcset=\"/b.png 1x, /c.png 2x\">\
+            <iframe src=\"/frame\"></iframe>\
+            <map><area href=\"/area\"></map>\
+            <form action=\"/search\"></form><form action=\"/login\" method=\"post\"></form>\
+            <a href=\"/anchor\">Anchor</a></html>"
+                .to_vec(),
+        );
+
+        let links: Vec<(String, LinkKind)> = crawling
+            .find_links()
+            .unwrap()
+            .into_iter()
+            .map(|link| (link.url.path().to_string(), link.kind))
+            .collect();
+        assert_eq!(
+            links,
+            vec![
+                ("/style.css".to_string(), LinkKind::Link),
+                ("/refresh".to_string(), LinkKind::MetaRefresh),
+                ("/a.png".to_string(), LinkKind::Image),
+                ("/b.png".to_string(), LinkKind::Image),
+                ("/c.png".to_string(), LinkKind::Image),
+                ("/frame".to_string(), LinkKind::Iframe),
+                ("/area".to_string(), LinkKind::Area),
+                ("/search".to_string(), LinkKind::Form),
+                ("/anchor".to_string(), LinkKind::Anchor),
+            ]
+        );
+        // only the default kinds are returned as URLs
+        assert_eq!(
+            crawling.find_urls(),
+            Some(vec![
+                get_url("http://example.com/style.css"),
+                get_url("http://example.com/area"),
+                get_url("http://example.com/anchor")
+            ])
+        );
+    }
+
+    #[test]
+    fn crawling_parse_refresh() {
+        assert_eq!(parse_refresh("5; url=/foo"), Some("/foo"));
+        assert_eq!(
+            parse_refresh("0;URL = \"http://example.com\""),
+            Some("http://example.com")
+        );
+        assert_eq!(parse_refresh("0; /next"), Some("/next"));
+        assert_eq!(parse_refresh("0; urls.html"), Some("urls.html"));
+        assert_eq!(parse_refresh("5"), None);
+        assert_eq!(parse_refresh("5; "), None);
+    }
+
+    #[test]
+    fn crawling_parse_srcset() {
+        assert_eq!(
+            parse_srcset("/a.png 1x, /b.png 2x"),
+            vec!["/a.png", "/b.png"]
+        );
+        assert_eq!(
+            parse_srcset("/a.png 1x,/b.png 2x"),
+            vec!["/a.png", "/b.png"]
+        );
+        assert_eq!(
+            parse_srcset("/w_100,h_50/a.png 100w, /w_200,h_100/a.png 200w"),
+            vec!["/w_100,h_50/a.png", "/w_200,h_100/a.png"]
+        );
+        assert_eq!(parse_srcset("/a.png, /b.png"), vec!["/a.png", "/b.png"]);
+        assert_eq!(parse_srcset(" , "), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn html_crawling_get_directives() {
+        let url = get_url("http://example.com");
+        let persister = get_mock_persister();
+
+        let crawling = |content: &[u8]| {
+            Crawling::new(
+                persister.clone(),
+                url.clone(),
+                "text/html",
+                content.to_vec(),
+            )
+        };
+
+        assert_eq!(
+            crawling(b"<html>Hello World</html>").get_directives(),
+            Directives::default()
+        );
+        assert_eq!(
+            crawling(b"<meta name=\"ROBOTS\" content=\"noindex, follow\">").get_directives(),
+            Directives {
+                noindex: true,
+                nofollow: false
+            }
+        );
+        assert_eq!(
+            crawling(b"<meta name=\"robots\" content=\"NOFOLLOW\">").get_directives(),
+            Directives {
+                noindex: false,
+                nofollow: true
+            }
+        );
+        assert_eq!(
+            crawling(b"<meta name=\"robots\" content=\"none\">").get_directives(),
+            Directives {
+                noindex: true,
+                nofollow: true
+            }
+        );
     }
 
     #[test]
@@ -249,7 +872,7 @@ mod tests {
             persister,
             url.clone(),
             "application/pdf",
-            (&[1, 2, 3]).to_vec(),
+            [1, 2, 3].to_vec(),
         );
 
         assert_eq!(crawling.kind, Kind::Pdf);
@@ -264,7 +887,7 @@ mod tests {
             persister,
             url,
             "application/foo",
-            (&[1, 2, 3, 4, 5, 6]).to_vec(),
+            [1, 2, 3, 4, 5, 6].to_vec(),
         );
 
         assert_eq!(crawling.kind, Kind::Unknown);
@@ -278,7 +901,7 @@ mod tests {
         let crawling = Crawling::new(persister, url, "text/html", b"Hello World!".to_vec());
         let result = crawling.write();
 
-        let dest_ref = crawling.persister.dest.borrow();
+        let dest_ref = crawling.persister.dest.lock().unwrap();
 
         assert_eq!(result.unwrap(), 49);
         assert_eq!(dest_ref.len(), 1);
@@ -290,6 +913,24 @@ mod tests {
         );
     }
 
+    #[test]
+    fn crawling_write_noindex() {
+        let url = get_url("http://example.com");
+        let persister = get_mock_persister();
+
+        let crawling = Crawling::new(
+            persister,
+            url,
+            "text/html",
+            b"<meta name=\"robots\" content=\"noindex\">Hello World!".to_vec(),
+        );
+        let result = crawling.write();
+
+        assert!(!crawling.should_persist());
+        assert_eq!(result.unwrap(), 0);
+        assert!(crawling.persister.dest.lock().unwrap().is_empty());
+    }
+
     #[test]
     fn crawling_get_domain() {
         let url = get_url("http://example.com/foo?bar&baz=qux#some");
@@ -315,17 +956,68 @@ mod tests {
             persister.clone(),
             url.clone(),
             "application/pdf",
-            (&[1, 2, 3]).to_vec(),
+            [1, 2, 3].to_vec(),
         );
         let crawling_unknown = Crawling::new(
             persister.clone(),
             url.clone(),
             "application/foo",
-            (&[1, 2, 3]).to_vec(),
+            [1, 2, 3].to_vec(),
         );
 
         assert_eq!(crawling_html.get_file_extension(), Some(".html"));
         assert_eq!(crawling_pdf.get_file_extension(), Some(".pdf"));
         assert_eq!(crawling_unknown.get_file_extension(), None);
     }
+
+    #[test]
+    fn link_to_string_and_back() {
+        let link = Link {
+            url: get_url("http://example.com/about"),
+            kind: LinkKind::Anchor,
+            nofollow: true,
+            text: Some("About\n  us".to_string()),
+        };
+        let value = link.to_string();
+        assert_eq!(value, "a nofollow http://example.com/about About us");
+        assert_eq!(
+            Link::from_str(&value).unwrap(),
+            Link {
+                text: Some("About us".to_string()),
+                ..link
+            }
+        );
+
+        let link = Link {
+            url: get_url("http://example.com/logo.png"),
+            kind: LinkKind::Image,
+            nofollow: false,
+            text: None,
+        };
+        assert_eq!(Link::from_str(&link.to_string()).unwrap(), link);
+        assert!(Link::from_str("a follow").is_err());
+        assert!(Link::from_str("video follow http://example.com/").is_err());
+    }
+
+    #[test]
+    fn crawling_decodes_content() {
+        let url = get_url("http://example.com");
+        let persister = get_mock_persister();
+
+        let crawling = Crawling::new(
+            persister.clone(),
+            url.clone(),
+            "text/html; charset=windows-1252",
+            b"<a href=\"/caf\xE9\">Caf\xE9</a>".to_vec(),
+        );
+        let links = crawling.find_links().unwrap();
+        assert_eq!(links[0].url.as_str(), "http://example.com/caf%C3%A9");
+        assert_eq!(links[0].text.as_deref(), Some("Café"));
+        let response = crawling.get_response();
+        assert_eq!(response.encoding, Some(encoding_rs::WINDOWS_1252));
+        assert_eq!(response.get_text().unwrap(), "<a href=\"/café\">Café</a>");
+
+        let crawling = Crawling::new(persister, url, "application/pdf", [1, 2, 3].to_vec());
+        assert_eq!(crawling.get_response().encoding, None);
+    }
 }
@@ -16,6 +16,26 @@ pub enum CrawlerError {
     PersistingError(String),
     RequestError(String),
     ContentTypeError(String),
+    SizeError(String),
+    WorkerError(String),
+}
+
+impl CrawlerError {
+    // the name of the variant, e.g. to count errors by their kind
+    pub fn get_kind(&self) -> &'static str {
+        match *self {
+            CrawlerError::ReqwestError(_) => "ReqwestError",
+            CrawlerError::ReqwestUrlError(_) => "ReqwestUrlError",
+            CrawlerError::IoError(_) => "IoError",
+            CrawlerError::ParsingError(_) => "ParsingError",
+            CrawlerError::FetchingError(_) => "FetchingError",
+            CrawlerError::PersistingError(_) => "PersistingError",
+            CrawlerError::RequestError(_) => "RequestError",
+            CrawlerError::ContentTypeError(_) => "ContentTypeError",
+            CrawlerError::SizeError(_) => "SizeError",
+            CrawlerError::WorkerError(_) => "WorkerError",
+        }
+    }
 }
 
 impl Error for CrawlerError {}
@@ -31,6 +51,8 @@ impl Display for CrawlerError {
             CrawlerError::PersistingError(ref err) => Display::fmt(err, f),
             CrawlerError::RequestError(ref err) => Display::fmt(err, f),
             CrawlerError::ContentTypeError(ref err) => Display::fmt(err, f),
+            CrawlerError::SizeError(ref err) => Display::fmt(err, f),
+            CrawlerError::WorkerError(ref err) => Display::fmt(err, f),
         }
     }
 }
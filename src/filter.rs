@@ -0,0 +1,282 @@
+use crate::error::CrawlerError::ParsingError;
+use crate::shared;
+use regex::Regex;
+use reqwest::Url;
+use std::fs;
+use std::str::FromStr;
+
+pub static EXCLUDED_EXTENSIONS: [&str; 15] = [
+    "css", "js", "png", "jpg", "jpeg", "gif", "tiff", "ico", "svg", "json", "woff2", "csv", "xls",
+    "xlsx",
+    // NOTE: we might want to re-include the following extensions in future releases
+    "xml",
+];
+static EXCLUDED_HOSTS: [&str; 7] = [
+    "google.com",
+    "google-analytics.com",
+    "googleapis.com",
+    "yahoo.com",
+    "bing.com",
+    // NOTE: we might want to re-include the following hosts in future releases
+    "facebook.com",
+    "twitter.com",
+];
+
+#[derive(Debug, Clone)]
+pub enum Pattern {
+    // `*` matches any number of characters and `?` a single one in the whole URL
+    Glob(Regex),
+    Regex(Regex),
+    // matches the host and all of its subdomains
+    Host(String),
+    // matches the extension of the last segment of the path
+    Extension(String),
+}
+
+impl Pattern {
+    pub fn matches(&self, url: &Url) -> bool {
+        match self {
+            Pattern::Glob(regex) | Pattern::Regex(regex) => regex.is_match(url.as_str()),
+            Pattern::Host(host) => url.host_str().is_some_and(|url_host| {
+                url_host == host || url_host.ends_with(format!(".{}", host).as_str())
+            }),
+            Pattern::Extension(extension) => get_extension(url)
+                .is_some_and(|url_extension| url_extension.eq_ignore_ascii_case(extension)),
+        }
+    }
+}
+
+impl PartialEq for Pattern {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Pattern::Glob(a), Pattern::Glob(b)) | (Pattern::Regex(a), Pattern::Regex(b)) => {
+                a.as_str() == b.as_str()
+            }
+            (Pattern::Host(a), Pattern::Host(b)) => a == b,
+            (Pattern::Extension(a), Pattern::Extension(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for Pattern {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (kind, pattern) = value
+            .split_once(':')
+            .ok_or_else(|| format!("Missing kind in pattern \"{}\"", value))?;
+        if pattern.is_empty() {
+            return Err(format!("Empty pattern \"{}\"", value));
+        }
+        match kind {
+            "glob" => Regex::new(&glob_to_regex(pattern))
+                .map(Pattern::Glob)
+                .map_err(|err| err.to_string()),
+            "regex" => Regex::new(pattern)
+                .map(Pattern::Regex)
+                .map_err(|err| err.to_string()),
+            "host" => Ok(Pattern::Host(pattern.to_lowercase())),
+            "ext" => Ok(Pattern::Extension(
+                pattern.trim_start_matches('.').to_lowercase(),
+            )),
+            _ => Err(format!("Unknown kind of pattern \"{}\"", value)),
+        }
+    }
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub enum Rule {
+    Include(Pattern),
+    Exclude(Pattern),
+}
+
+impl FromStr for Rule {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().split_once(char::is_whitespace) {
+            Some(("include", pattern)) => Ok(Rule::Include(pattern.trim().parse()?)),
+            Some(("exclude", pattern)) => Ok(Rule::Exclude(pattern.trim().parse()?)),
+            _ => Err(format!("Unknown rule \"{}\"", value)),
+        }
+    }
+}
+
+// a URL is allowed if it matches none of the exclude rules and, if there are any include rules, at
+// least one of them
+#[derive(PartialEq, Debug, Clone)]
+pub struct UrlFilter {
+    rules: Vec<Rule>,
+}
+
+impl Default for UrlFilter {
+    fn default() -> Self {
+        let extensions = EXCLUDED_EXTENSIONS
+            .iter()
+            .map(|extension| Rule::Exclude(Pattern::Extension(extension.to_string())));
+        let hosts = EXCLUDED_HOSTS
+            .iter()
+            .map(|host| Rule::Exclude(Pattern::Host(host.to_string())));
+        UrlFilter::new(extensions.chain(hosts).collect())
+    }
+}
+
+impl UrlFilter {
+    pub fn new(rules: Vec<Rule>) -> Self {
+        UrlFilter { rules }
+    }
+
+    // reads one rule per line, e.g. `exclude ext:pdf`. Empty lines and lines starting with `#` are
+    // skipped
+    pub fn load(path: &str) -> shared::Result<Self> {
+        let content = fs::read_to_string(path)?;
+        let mut rules = vec![];
+        for (idx, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let rule = line
+                .parse::<Rule>()
+                .map_err(|err| ParsingError(format!("{} in line {} of {}", err, idx + 1, path)))?;
+            rules.push(rule);
+        }
+        Ok(UrlFilter::new(rules))
+    }
+
+    pub fn allows(&self, url: &Url) -> bool {
+        let mut has_includes = false;
+        let mut included = false;
+        for rule in self.rules.iter() {
+            match rule {
+                Rule::Exclude(pattern) if pattern.matches(url) => return false,
+                Rule::Include(pattern) => {
+                    has_includes = true;
+                    included = included || pattern.matches(url);
+                }
+                _ => {}
+            }
+        }
+        !has_includes || included
+    }
+}
+
+fn get_extension(url: &Url) -> Option<&str> {
+    let segment = url.path().rsplit('/').next()?;
+    let (name, extension) = segment.rsplit_once('.')?;
+    if name.is_empty() || extension.is_empty() {
+        return None;
+    }
+    Some(extension)
+}
+
+fn glob_to_regex(glob: &str) -> String {
+    let mut regex = String::from("^");
+    for c in glob.chars() {
+        match c {
+            '*' => regex.push_str(".*"),
+            '?' => regex.push('.'),
+            _ => regex.push_str(&regex::escape(c.encode_utf8(&mut [0; 4]))),
+        }
+    }
+    regex.push('$');
+    regex
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::filter::{Pattern, Rule, UrlFilter};
+    use reqwest::Url;
+    use std::env;
+    use std::fs;
+    use std::process;
+
+    fn get_url(url: &str) -> Url {
+        Url::parse(url).unwrap()
+    }
+
+    fn rule(rule: &str) -> Rule {
+        rule.parse().unwrap()
+    }
+
+    #[test]
+    fn filter_default() {
+        let filter = UrlFilter::default();
+
+        assert!(filter.allows(&get_url("http://example.com/index.html")));
+        assert!(filter.allows(&get_url("http://example.com/docs.jsonnet/")));
+        assert!(filter.allows(&get_url("http://mybing.org/")));
+        assert!(filter.allows(&get_url("http://example.com/data.json.html")));
+        assert!(!filter.allows(&get_url("http://example.com/data.json")));
+        assert!(!filter.allows(&get_url("http://example.com/a/style.CSS?v=1")));
+        assert!(!filter.allows(&get_url("http://bing.com/")));
+        assert!(!filter.allows(&get_url("https://www.facebook.com/jdoe")));
+    }
+
+    #[test]
+    fn filter_patterns() {
+        let glob = "glob:https://example.com/docs/*"
+            .parse::<Pattern>()
+            .unwrap();
+        assert!(glob.matches(&get_url("https://example.com/docs/a/b")));
+        assert!(!glob.matches(&get_url("https://example.com/blog/")));
+        assert!(!glob.matches(&get_url("https://example.com.evil.com/docs/")));
+
+        let regex = r"regex:/\d+$".parse::<Pattern>().unwrap();
+        assert!(regex.matches(&get_url("http://example.com/posts/42")));
+        assert!(!regex.matches(&get_url("http://example.com/posts/new")));
+
+        let host = "host:Example.com".parse::<Pattern>().unwrap();
+        assert!(host.matches(&get_url("http://example.com/")));
+        assert!(host.matches(&get_url("http://docs.example.com/")));
+        assert!(!host.matches(&get_url("http://myexample.com/")));
+
+        let extension = "ext:.pdf".parse::<Pattern>().unwrap();
+        assert!(extension.matches(&get_url("http://example.com/a.PDF")));
+        assert!(!extension.matches(&get_url("http://example.com/a.pdf/b")));
+        assert!(!extension.matches(&get_url("http://example.com/.pdf")));
+
+        assert!("foo:bar".parse::<Pattern>().is_err());
+        assert!("regex:(".parse::<Pattern>().is_err());
+        assert!("host:".parse::<Pattern>().is_err());
+    }
+
+    #[test]
+    fn filter_include_and_exclude() {
+        let filter = UrlFilter::new(vec![
+            rule("include host:example.com"),
+            rule("include host:jdoe.com"),
+            rule("exclude glob:*/private/*"),
+        ]);
+
+        assert!(filter.allows(&get_url("http://example.com/")));
+        assert!(filter.allows(&get_url("http://jdoe.com/")));
+        assert!(!filter.allows(&get_url("http://other.com/")));
+        assert!(!filter.allows(&get_url("http://example.com/private/a")));
+        assert!(UrlFilter::new(vec![]).allows(&get_url("http://bing.com/a.json")));
+    }
+
+    #[test]
+    fn filter_load() {
+        let path = env::temp_dir().join(format!("crawler-filter-{}", process::id()));
+        let path = path.to_str().unwrap();
+
+        fs::write(
+            path,
+            "# docs only\ninclude glob:*/docs/*\n\nexclude ext:pdf\n",
+        )
+        .unwrap();
+        assert_eq!(
+            UrlFilter::load(path).unwrap(),
+            UrlFilter::new(vec![rule("include glob:*/docs/*"), rule("exclude ext:pdf")])
+        );
+
+        fs::write(path, "include glob:*/docs/*\nignore ext:pdf\n").unwrap();
+        assert!(UrlFilter::load(path)
+            .unwrap_err()
+            .to_string()
+            .contains("line 2"));
+        fs::remove_file(path).unwrap();
+    }
+}
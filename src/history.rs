@@ -0,0 +1,384 @@
+use crate::crawling::Link;
+use crate::error::CrawlerError::{self, ParsingError, RequestError};
+use crate::response::Response;
+use crate::shared;
+use reqwest::header::{
+    HeaderMap, HeaderValue, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED,
+};
+use reqwest::Url;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, BufWriter, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+// what's known about a page from a previous crawl so that it's only fetched again if it changed
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct Revision {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    // the page had a `nofollow` directive
+    pub nofollow: bool,
+    // the links found on the page. They're followed again if the page didn't change
+    pub links: Vec<Link>,
+}
+
+impl Revision {
+    // `None` if the response has neither an `ETag` nor a `Last-Modified` header
+    pub fn from_response(response: &Response, nofollow: bool) -> Option<Self> {
+        let get_header = |name| {
+            response
+                .headers
+                .get(name)
+                .and_then(|value: &HeaderValue| value.to_str().ok())
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty())
+        };
+        let etag = get_header(ETAG);
+        let last_modified = get_header(LAST_MODIFIED);
+        if etag.is_none() && last_modified.is_none() {
+            return None;
+        }
+        Some(Revision {
+            etag,
+            last_modified,
+            nofollow,
+            links: vec![],
+        })
+    }
+
+    // the headers of a conditional request which is answered with a `304` if nothing changed
+    pub fn to_headers(&self) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        let values = [
+            (IF_NONE_MATCH, &self.etag),
+            (IF_MODIFIED_SINCE, &self.last_modified),
+        ];
+        for (name, value) in values.iter() {
+            if let Some(value) = value.as_ref().and_then(|value| value.parse().ok()) {
+                headers.insert(name.clone(), value);
+            }
+        }
+        headers
+    }
+}
+
+// a permanent error which means the page is gone, so its revision isn't needed anymore
+pub fn is_gone(err: &CrawlerError) -> bool {
+    match err {
+        // e.g. "404 Not Found"
+        RequestError(status) => ["404", "410"].contains(&status.split(' ').next().unwrap_or("")),
+        _ => false,
+    }
+}
+
+// the revisions of all pages of previous crawls by their URL. Only the URLs and where their pages
+// start are kept in memory, the revisions are read from the file when they're asked for. Changes
+// are appended to a journal next to the file until it's written again
+pub struct History {
+    path: PathBuf,
+    file: Option<File>,
+    pages: BTreeMap<Url, u64>,
+    journal: Option<File>,
+    // the pages which changed since the file was written and where they start in the journal.
+    // `None` for pages which were removed
+    changes: HashMap<Url, Option<u64>>,
+}
+
+impl History {
+    // a missing file means that nothing was crawled before
+    pub fn open(path: &Path) -> shared::Result<Self> {
+        let mut history = History {
+            path: path.to_path_buf(),
+            file: None,
+            pages: BTreeMap::new(),
+            journal: None,
+            changes: HashMap::new(),
+        };
+        if !path.exists() {
+            return Ok(history);
+        }
+        let mut reader = BufReader::new(File::open(path)?);
+        let mut offset = 0;
+        let mut line = String::new();
+        let mut idx = 0;
+        let mut has_page = false;
+        loop {
+            line.clear();
+            let len = reader.read_line(&mut line)?;
+            if len == 0 {
+                break;
+            }
+            idx += 1;
+            let invalid = || ParsingError(format!("Invalid history in line {}", idx));
+            let (key, value) = split_line(&line);
+            if key == "page" {
+                let url = Url::parse(value).map_err(|_| invalid())?;
+                history.pages.insert(url, offset);
+                has_page = true;
+            } else if !has_page || parse_line(&mut Revision::default(), key, value).is_none() {
+                // every other line belongs to the last page
+                return Err(invalid());
+            }
+            offset += len as u64;
+        }
+        history.file = Some(reader.into_inner());
+        Ok(history)
+    }
+
+    pub fn get(&mut self, url: &Url) -> Option<Arc<Revision>> {
+        let (file, offset) = match self.changes.get(url) {
+            Some(offset) => (self.journal.as_mut()?, (*offset)?),
+            None => (self.file.as_mut()?, *self.pages.get(url)?),
+        };
+        match read_revision(file, offset) {
+            Ok(revision) => revision.map(Arc::new),
+            Err(err) => {
+                loge!(format!("Failed to read history: {}", err));
+                None
+            }
+        }
+    }
+
+    pub fn insert(&mut self, url: Url, revision: &Revision) -> shared::Result<()> {
+        if self.journal.is_none() {
+            let journal = OpenOptions::new()
+                .read(true)
+                .write(true)
+                .create(true)
+                .truncate(true)
+                .open(self.get_journal_path())?;
+            self.journal = Some(journal);
+        }
+        let journal = self.journal.as_mut().unwrap();
+        let offset = journal.seek(SeekFrom::End(0))?;
+        let mut writer = BufWriter::new(journal);
+        write_revision(&mut writer, &url, revision)?;
+        writer.flush()?;
+        self.changes.insert(url, Some(offset));
+        Ok(())
+    }
+
+    pub fn remove(&mut self, url: &Url) {
+        if self.pages.contains_key(url) || self.changes.contains_key(url) {
+            self.changes.insert(url.clone(), None);
+        }
+    }
+
+    pub fn count_pages(&self) -> usize {
+        let added = self
+            .changes
+            .iter()
+            .filter(|(url, offset)| offset.is_some() && !self.pages.contains_key(url))
+            .count();
+        let removed = self
+            .changes
+            .iter()
+            .filter(|(url, offset)| offset.is_none() && self.pages.contains_key(url))
+            .count();
+        self.pages.len() + added - removed
+    }
+
+    // merges the changes into the file. It's replaced at once so that a crash while writing leaves
+    // the last state intact
+    pub fn write(&mut self) -> shared::Result<()> {
+        let tmp_path = self.path.with_extension("tmp");
+        let mut writer = BufWriter::new(File::create(&tmp_path)?);
+        let mut urls: BTreeSet<&Url> = self.pages.keys().collect();
+        urls.extend(self.changes.keys());
+        let mut pages = BTreeMap::new();
+        let mut offset = 0;
+        for url in urls {
+            let (file, page_offset) = match self.changes.get(url) {
+                Some(None) => continue,
+                Some(Some(page_offset)) => (self.journal.as_mut(), *page_offset),
+                None => (self.file.as_mut(), self.pages[url]),
+            };
+            let file = file.ok_or_else(|| ParsingError("History without file".to_string()))?;
+            let lines = read_lines(file, page_offset)?;
+            pages.insert(url.clone(), offset);
+            for line in lines {
+                writeln!(writer, "{}", line)?;
+                offset += line.len() as u64 + 1;
+            }
+        }
+        writer.flush()?;
+        drop(writer);
+        fs::rename(&tmp_path, &self.path)?;
+
+        self.file = Some(File::open(&self.path)?);
+        self.pages = pages;
+        self.changes.clear();
+        if self.journal.take().is_some() {
+            fs::remove_file(self.get_journal_path())?;
+        }
+        Ok(())
+    }
+
+    fn get_journal_path(&self) -> PathBuf {
+        self.path.with_extension("journal")
+    }
+}
+
+// e.g. `etag "abc"`
+fn split_line(line: &str) -> (&str, &str) {
+    let line = line.trim_end_matches(['\r', '\n']);
+    line.split_once(' ').unwrap_or((line, ""))
+}
+
+fn parse_line(revision: &mut Revision, key: &str, value: &str) -> Option<()> {
+    match key {
+        "etag" => revision.etag = Some(value.to_string()),
+        "last_modified" => revision.last_modified = Some(value.to_string()),
+        "nofollow" => revision.nofollow = true,
+        "link" => revision.links.push(value.parse().ok()?),
+        _ => return None,
+    }
+    Some(())
+}
+
+fn write_revision<W>(writer: &mut W, url: &Url, revision: &Revision) -> io::Result<()>
+where
+    W: Write,
+{
+    writeln!(writer, "page {}", url)?;
+    if let Some(etag) = &revision.etag {
+        writeln!(writer, "etag {}", etag)?;
+    }
+    if let Some(last_modified) = &revision.last_modified {
+        writeln!(writer, "last_modified {}", last_modified)?;
+    }
+    if revision.nofollow {
+        writeln!(writer, "nofollow")?;
+    }
+    for link in revision.links.iter() {
+        writeln!(writer, "link {}", link)?;
+    }
+    Ok(())
+}
+
+// the lines of the page which starts at the offset, including its `page` line
+fn read_lines(file: &mut File, offset: u64) -> io::Result<Vec<String>> {
+    file.seek(SeekFrom::Start(offset))?;
+    let mut lines = vec![];
+    for line in BufReader::new(file).lines() {
+        let line = line?;
+        if !lines.is_empty() && line.starts_with("page ") {
+            break;
+        }
+        lines.push(line);
+    }
+    Ok(lines)
+}
+
+fn read_revision(file: &mut File, offset: u64) -> io::Result<Option<Revision>> {
+    let mut revision = Revision::default();
+    for line in read_lines(file, offset)?.iter().skip(1) {
+        let (key, value) = split_line(line);
+        if parse_line(&mut revision, key, value).is_none() {
+            return Ok(None);
+        }
+    }
+    Ok(Some(revision))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::crawling::{Link, LinkKind};
+    use crate::error::CrawlerError::{PersistingError, RequestError};
+    use crate::history::{is_gone, History, Revision};
+    use crate::response::Response;
+    use reqwest::header::{ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+    use reqwest::Url;
+    use std::env;
+    use std::fs;
+    use std::process;
+
+    #[test]
+    fn revision_from_response() {
+        let url = Url::parse("http://example.com/").unwrap();
+        let mut response = Response::new(url, "text/html", vec![]);
+        assert_eq!(Revision::from_response(&response, false), None);
+
+        response.headers.insert(ETAG, "\"abc\"".parse().unwrap());
+        let revision = Revision::from_response(&response, true).unwrap();
+        assert_eq!(revision.etag.as_deref(), Some("\"abc\""));
+        assert_eq!(revision.last_modified, None);
+        assert!(revision.nofollow);
+
+        let headers = revision.to_headers();
+        assert_eq!(headers[IF_NONE_MATCH], "\"abc\"");
+        assert!(!headers.contains_key(IF_MODIFIED_SINCE));
+
+        response.headers.clear();
+        let date = "Wed, 21 Oct 2015 07:28:00 GMT";
+        response
+            .headers
+            .insert(LAST_MODIFIED, date.parse().unwrap());
+        let revision = Revision::from_response(&response, false).unwrap();
+        assert_eq!(revision.to_headers()[IF_MODIFIED_SINCE], date);
+    }
+
+    #[test]
+    fn history_write_and_read() {
+        let path = env::temp_dir().join(format!("crawler-history-{}", process::id()));
+        let mut history = History::open(&path).unwrap();
+        let home = Url::parse("http://example.com/").unwrap();
+        let home_revision = Revision {
+            etag: Some("W/\"abc def\"".to_string()),
+            last_modified: Some("Wed, 21 Oct 2015 07:28:00 GMT".to_string()),
+            nofollow: true,
+            links: vec![Link {
+                url: Url::parse("http://example.com/about").unwrap(),
+                kind: LinkKind::Anchor,
+                nofollow: false,
+                text: Some("About us".to_string()),
+            }],
+        };
+        history.insert(home.clone(), &home_revision).unwrap();
+        let about = Url::parse("http://example.com/about").unwrap();
+        let about_revision = Revision {
+            etag: Some("\"123\"".to_string()),
+            ..Revision::default()
+        };
+        history.insert(about.clone(), &about_revision).unwrap();
+        assert_eq!(history.get(&home).as_deref(), Some(&home_revision));
+
+        history.write().unwrap();
+        let mut restored = History::open(&path).unwrap();
+        assert_eq!(restored.count_pages(), 2);
+        assert_eq!(restored.get(&home).as_deref(), Some(&home_revision));
+        assert_eq!(restored.get(&about).as_deref(), Some(&about_revision));
+
+        // changes are only merged into the file once it's written
+        let changed = Revision {
+            etag: Some("\"456\"".to_string()),
+            ..Revision::default()
+        };
+        restored.insert(about.clone(), &changed).unwrap();
+        restored.remove(&home);
+        assert_eq!(restored.get(&home), None);
+        assert_eq!(restored.get(&about).as_deref(), Some(&changed));
+        assert_eq!(restored.count_pages(), 1);
+        assert_eq!(History::open(&path).unwrap().count_pages(), 2);
+        restored.write().unwrap();
+        let mut restored = History::open(&path).unwrap();
+        assert_eq!(restored.count_pages(), 1);
+        assert_eq!(restored.get(&about).as_deref(), Some(&changed));
+
+        fs::write(&path, "etag \"abc\"\n").unwrap();
+        let err = History::open(&path).err().unwrap();
+        assert!(err.to_string().contains("line 1"));
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn history_is_gone() {
+        assert!(is_gone(&RequestError("404 Not Found".to_string())));
+        assert!(is_gone(&RequestError("410 Gone".to_string())));
+        assert!(!is_gone(&RequestError(
+            "503 Service Unavailable".to_string()
+        )));
+        assert!(!is_gone(&PersistingError("Disk full".to_string())));
+    }
+}
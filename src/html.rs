@@ -0,0 +1,425 @@
+// elements whose content is text and never contains any other elements
+static RAW_TEXT_ELEMENTS: [&str; 4] = ["script", "style", "textarea", "title"];
+// the named entities of the characters from `&nbsp;` (U+00A0) to `&yuml;` (U+00FF) in order
+static LATIN1_ENTITIES: [&str; 96] = [
+    "nbsp", "iexcl", "cent", "pound", "curren", "yen", "brvbar", "sect", "uml", "copy", "ordf",
+    "laquo", "not", "shy", "reg", "macr", "deg", "plusmn", "sup2", "sup3", "acute", "micro",
+    "para", "middot", "cedil", "sup1", "ordm", "raquo", "frac14", "frac12", "frac34", "iquest",
+    "Agrave", "Aacute", "Acirc", "Atilde", "Auml", "Aring", "AElig", "Ccedil", "Egrave", "Eacute",
+    "Ecirc", "Euml", "Igrave", "Iacute", "Icirc", "Iuml", "ETH", "Ntilde", "Ograve", "Oacute",
+    "Ocirc", "Otilde", "Ouml", "times", "Oslash", "Ugrave", "Uacute", "Ucirc", "Uuml", "Yacute",
+    "THORN", "szlig", "agrave", "aacute", "acirc", "atilde", "auml", "aring", "aelig", "ccedil",
+    "egrave", "eacute", "ecirc", "euml", "igrave", "iacute", "icirc", "iuml", "eth", "ntilde",
+    "ograve", "oacute", "ocirc", "otilde", "ouml", "divide", "oslash", "ugrave", "uacute", "ucirc",
+    "uuml", "yacute", "thorn", "yuml",
+];
+// further named entities which are common in text, e.g. dashes and typographic quotes.
+// NOTE: the less common ones of the full HTML5 list (over 2000) aren't decoded
+static ENTITIES: [(&str, char); 40] = [
+    ("OElig", '\u{152}'),
+    ("oelig", '\u{153}'),
+    ("Scaron", '\u{160}'),
+    ("scaron", '\u{161}'),
+    ("Yuml", '\u{178}'),
+    ("fnof", '\u{192}'),
+    ("circ", '\u{2c6}'),
+    ("tilde", '\u{2dc}'),
+    ("ensp", '\u{2002}'),
+    ("emsp", '\u{2003}'),
+    ("thinsp", '\u{2009}'),
+    ("zwnj", '\u{200c}'),
+    ("zwj", '\u{200d}'),
+    ("lrm", '\u{200e}'),
+    ("rlm", '\u{200f}'),
+    ("ndash", '\u{2013}'),
+    ("mdash", '\u{2014}'),
+    ("lsquo", '\u{2018}'),
+    ("rsquo", '\u{2019}'),
+    ("sbquo", '\u{201a}'),
+    ("ldquo", '\u{201c}'),
+    ("rdquo", '\u{201d}'),
+    ("bdquo", '\u{201e}'),
+    ("dagger", '\u{2020}'),
+    ("Dagger", '\u{2021}'),
+    ("bull", '\u{2022}'),
+    ("hellip", '\u{2026}'),
+    ("permil", '\u{2030}'),
+    ("prime", '\u{2032}'),
+    ("Prime", '\u{2033}'),
+    ("lsaquo", '\u{2039}'),
+    ("rsaquo", '\u{203a}'),
+    ("euro", '\u{20ac}'),
+    ("trade", '\u{2122}'),
+    ("larr", '\u{2190}'),
+    ("uarr", '\u{2191}'),
+    ("rarr", '\u{2192}'),
+    ("darr", '\u{2193}'),
+    ("harr", '\u{2194}'),
+    ("minus", '\u{2212}'),
+];
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct Tag {
+    pub name: String,
+    pub attributes: Vec<(String, String)>,
+    // the text between `<a>` and `</a>` without any tags and with collapsed whitespace
+    pub text: Option<String>,
+}
+
+impl Tag {
+    pub fn get_attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+// returns all start tags of the document. Comments and the content of elements like `<script>`
+// and `<style>` are skipped
+pub fn parse_tags(html: &str) -> Vec<Tag> {
+    let bytes = html.as_bytes();
+    let mut tags: Vec<Tag> = vec![];
+    let mut pos = 0;
+    while let Some(idx) = html[pos..].find('<') {
+        pos += idx;
+        let rest = &html[pos..];
+        if rest.starts_with("<!--") {
+            pos = skip_past(html, pos + 4, "-->");
+        } else if rest.starts_with("<!") || rest.starts_with("<?") || rest.starts_with("</") {
+            pos = skip_past(html, pos + 2, ">");
+        } else if bytes.len() > pos + 1 && bytes[pos + 1].is_ascii_alphabetic() {
+            let (mut tag, end) = parse_tag(html, pos + 1);
+            pos = end;
+            if tag.name == "a" {
+                tag.text = parse_text(html, pos, &tag.name);
+            }
+            if RAW_TEXT_ELEMENTS.contains(&tag.name.as_str()) {
+                pos = find_closing_tag(html, pos, &tag.name);
+            }
+            tags.push(tag);
+        } else {
+            pos += 1;
+        }
+    }
+    tags
+}
+
+// returns the position right after `pattern` or the end of the document
+fn skip_past(html: &str, pos: usize, pattern: &str) -> usize {
+    match html[pos..].find(pattern) {
+        Some(idx) => pos + idx + pattern.len(),
+        None => html.len(),
+    }
+}
+
+// returns the position of the next closing tag with the name or the end of the document. The name
+// is compared in place so that the document isn't copied for every element
+fn find_closing_tag(html: &str, pos: usize, name: &str) -> usize {
+    html[pos..]
+        .match_indices("</")
+        .map(|(idx, _)| pos + idx)
+        .find(|&idx| {
+            let rest = &html.as_bytes()[idx + 2..];
+            rest.len() >= name.len()
+                && rest[..name.len()].eq_ignore_ascii_case(name.as_bytes())
+                && rest
+                    .get(name.len())
+                    .is_none_or(|b| b.is_ascii_whitespace() || *b == b'>' || *b == b'/')
+        })
+        .unwrap_or(html.len())
+}
+
+// returns the text up to the closing tag of an element. Nested tags are left out
+fn parse_text(html: &str, pos: usize, name: &str) -> Option<String> {
+    let end = find_closing_tag(html, pos, name);
+
+    let mut text = String::new();
+    let mut rest = &html[pos..end];
+    while let Some(idx) = rest.find('<') {
+        text.push_str(&rest[..idx]);
+        text.push(' ');
+        rest = match rest[idx..].find('>') {
+            Some(close) => &rest[idx + close + 1..],
+            None => "",
+        };
+    }
+    text.push_str(rest);
+    let text = decode_entities(&text)
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ");
+    if text.is_empty() {
+        return None;
+    }
+    Some(text)
+}
+
+fn parse_tag(html: &str, start: usize) -> (Tag, usize) {
+    let bytes = html.as_bytes();
+    let is_delimiter = |b: u8| b.is_ascii_whitespace() || b == b'/' || b == b'>';
+
+    let mut pos = start;
+    while pos < bytes.len() && !is_delimiter(bytes[pos]) {
+        pos += 1;
+    }
+    let name = html[start..pos].to_ascii_lowercase();
+
+    let mut attributes: Vec<(String, String)> = vec![];
+    loop {
+        while pos < bytes.len() && (bytes[pos].is_ascii_whitespace() || bytes[pos] == b'/') {
+            pos += 1;
+        }
+        if pos >= bytes.len() {
+            break;
+        }
+        if bytes[pos] == b'>' {
+            pos += 1;
+            break;
+        }
+
+        let name_start = pos;
+        while pos < bytes.len() && !is_delimiter(bytes[pos]) && bytes[pos] != b'=' {
+            pos += 1;
+        }
+        // an attribute name can start with `=` which makes sure we're always moving forward
+        if pos == name_start {
+            pos += 1;
+        }
+        let key = html[name_start..pos].to_ascii_lowercase();
+
+        while pos < bytes.len() && bytes[pos].is_ascii_whitespace() {
+            pos += 1;
+        }
+        let mut value = String::new();
+        if pos < bytes.len() && bytes[pos] == b'=' {
+            pos += 1;
+            while pos < bytes.len() && bytes[pos].is_ascii_whitespace() {
+                pos += 1;
+            }
+            if pos < bytes.len() && (bytes[pos] == b'"' || bytes[pos] == b'\'') {
+                let quote = bytes[pos] as char;
+                let value_start = pos + 1;
+                let value_end = match html[value_start..].find(quote) {
+                    Some(idx) => value_start + idx,
+                    None => html.len(),
+                };
+                value = decode_entities(&html[value_start..value_end]);
+                pos = (value_end + 1).min(html.len());
+            } else {
+                let value_start = pos;
+                while pos < bytes.len() && !bytes[pos].is_ascii_whitespace() && bytes[pos] != b'>' {
+                    pos += 1;
+                }
+                value = decode_entities(&html[value_start..pos]);
+            }
+        }
+
+        // only the first occurrence of an attribute counts
+        if !attributes.iter().any(|(existing, _)| existing == &key) {
+            attributes.push((key, value));
+        }
+    }
+
+    let tag = Tag {
+        name,
+        attributes,
+        text: None,
+    };
+    (tag, pos)
+}
+
+pub fn decode_entities(value: &str) -> String {
+    let mut decoded = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(idx) = rest.find('&') {
+        decoded.push_str(&rest[..idx]);
+        rest = &rest[idx..];
+        // entities are short so we don't have to look far for the terminating `;`
+        let entity = rest
+            .char_indices()
+            .take(12)
+            .find(|(_, c)| *c == ';')
+            .and_then(|(end, _)| decode_entity(&rest[1..end]).map(|c| (c, end)));
+        match entity {
+            Some((c, end)) => {
+                decoded.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+fn decode_entity(entity: &str) -> Option<char> {
+    if let Some(number) = entity.strip_prefix('#') {
+        let code = match number
+            .strip_prefix('x')
+            .or_else(|| number.strip_prefix('X'))
+        {
+            Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+            None => number.parse::<u32>().ok()?,
+        };
+        return std::char::from_u32(code);
+    }
+    match entity {
+        "amp" => Some('&'),
+        "lt" => Some('<'),
+        "gt" => Some('>'),
+        "quot" => Some('"'),
+        "apos" => Some('\''),
+        _ => LATIN1_ENTITIES
+            .iter()
+            .position(|name| *name == entity)
+            .and_then(|idx| std::char::from_u32(0xa0 + idx as u32))
+            .or_else(|| {
+                ENTITIES
+                    .iter()
+                    .find(|(name, _)| *name == entity)
+                    .map(|(_, c)| *c)
+            }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::html::{decode_entities, parse_tags, Tag};
+
+    fn tag(name: &str, attributes: &[(&str, &str)]) -> Tag {
+        Tag {
+            name: name.to_string(),
+            attributes: attributes
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect(),
+            text: None,
+        }
+    }
+
+    fn anchor(href: &str, text: &str) -> Tag {
+        Tag {
+            text: Some(text.to_string()),
+            ..tag("a", &[("href", href)])
+        }
+    }
+
+    #[test]
+    fn html_parse_tags_quoting_styles() {
+        let tags =
+            parse_tags(r#"<a href="double">1</a><a href='single'>2</a><A HREF=unquoted>3</A>"#);
+
+        assert_eq!(
+            tags,
+            vec![
+                anchor("double", "1"),
+                anchor("single", "2"),
+                anchor("unquoted", "3"),
+            ]
+        );
+    }
+
+    #[test]
+    fn html_parse_tags_attributes() {
+        let tags = parse_tags(
+            "<img src = \"a.png\" alt='A > B' hidden data-x=1/><br/><input value=\"x\" value=\"y\">",
+        );
+
+        assert_eq!(
+            tags,
+            vec![
+                tag(
+                    "img",
+                    &[
+                        ("src", "a.png"),
+                        ("alt", "A > B"),
+                        ("hidden", ""),
+                        ("data-x", "1/")
+                    ]
+                ),
+                tag("br", &[]),
+                tag("input", &[("value", "x")]),
+            ]
+        );
+        assert_eq!(tags[0].get_attribute("alt"), Some("A > B"));
+        assert_eq!(tags[0].get_attribute("foo"), None);
+    }
+
+    #[test]
+    fn html_parse_tags_skips_comments_and_raw_text() {
+        let tags = parse_tags(
+            "<!doctype html><!-- <a href=\"comment\"> --><script>var a = '<a href=\"script\">';\
+            </script><style>a { content: '<a href=\"style\">' }</STYLE><p>1 < 2</p>\
+            <?xml foo?><a href=\"link\">",
+        );
+
+        assert_eq!(
+            tags,
+            vec![
+                tag("script", &[]),
+                tag("style", &[]),
+                tag("p", &[]),
+                tag("a", &[("href", "link")]),
+            ]
+        );
+    }
+
+    #[test]
+    fn html_parse_tags_raw_text_closing_tag() {
+        // the closing tag has to end right after its name
+        let tags = parse_tags("<script>a = '</scripts>';</SCRIPT ><a href=\"link\">");
+        assert_eq!(
+            tags,
+            vec![tag("script", &[]), tag("a", &[("href", "link")])]
+        );
+    }
+
+    #[test]
+    fn html_parse_tags_unterminated() {
+        assert_eq!(
+            parse_tags("<a href=\"foo"),
+            vec![tag("a", &[("href", "foo")])]
+        );
+        assert_eq!(parse_tags("<!-- <a href=\"foo\">"), vec![]);
+        assert_eq!(parse_tags("<"), vec![]);
+    }
+
+    #[test]
+    fn html_parse_tags_anchor_text() {
+        let tags = parse_tags(
+            "<a href=\"/a\"> Rust  <b>&amp;</b>\n Go </a><a href=\"/b\"><img src=\"b.png\"></a>\
+            <a href=\"/c\">Open <abbr>ABBR</abbr>",
+        );
+        let anchors: Vec<Tag> = tags.into_iter().filter(|tag| tag.name == "a").collect();
+
+        assert_eq!(
+            anchors,
+            vec![
+                anchor("/a", "Rust & Go"),
+                tag("a", &[("href", "/b")]),
+                anchor("/c", "Open ABBR"),
+            ]
+        );
+    }
+
+    #[test]
+    fn html_decode_entities() {
+        assert_eq!(decode_entities("/a?b=1&amp;c=2"), "/a?b=1&c=2");
+        assert_eq!(decode_entities("&lt;&gt;&quot;&apos;"), "<>\"'");
+        assert_eq!(decode_entities("&#47;foo&#x2F;bar&#X2f;"), "/foo/bar/");
+        assert_eq!(decode_entities("/a?b=1&c=2"), "/a?b=1&c=2");
+        assert_eq!(decode_entities("&unknown; & &amp"), "&unknown; & &amp");
+        assert_eq!(
+            decode_entities("caf&eacute;&nbsp;&mdash;&hellip;"),
+            "café\u{a0}—…"
+        );
+        assert_eq!(
+            decode_entities("&Agrave;&yuml;&ldquo;&euro;&rdquo;"),
+            "Àÿ“€”"
+        );
+    }
+}
@@ -1,74 +1,144 @@
+use crate::crawling::SchemePolicy;
+use crate::history::Revision;
+use crate::priority::BreadthFirst;
+use crate::response::Response;
+use crate::seen::{fingerprint, SeenFilter};
 use crate::shared;
-use crate::traits::Fetch;
+use crate::traits::{Fetch, Score};
 use reqwest::Url;
-use std::collections::{HashSet, VecDeque};
-use std::hash::Hash;
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::hash::{Hash, Hasher};
+use std::io::{self, BufRead, BufReader, BufWriter, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 
-lazy_static! {
-    pub static ref BLACKLIST_CONTENT_TYPES: [&'static str; 15] = [
-        "css", "js", "png", "jpg", "jpeg", "gif", "tiff", "ico", "svg", "json", "woff2",
-        "csv", "xls", "xlsx",
-        // NOTE: we might want to re-include the following content types in future releases
-        "xml"
-    ];
-    pub static ref BLACKLIST_DOMAINS: [&'static str; 7] = [
-        "google", "google-analytics", "googleapis", "yahoo", "bing",
-        // NOTE: we might want to re-include the following domains in future releases
-        "facebook", "twitter"
-    ];
-}
+// makes the names of spill files unique within the process
+static SPILL_FILE_ID: AtomicUsize = AtomicUsize::new(0);
+// the maximum number of values which are looked at to find one which can be dequeued
+static MAX_SCANNED: usize = 10_000;
 
-#[derive(Eq, PartialEq, Hash, Clone, Debug)]
+#[derive(Clone, Debug)]
 pub struct Job<T>
 where
     T: Fetch,
 {
     fetcher: Arc<T>,
     url: Url,
+    depth: usize,
+    anchor_text: Option<String>,
+    // the number of times fetching the job was retried
+    retries: usize,
+    // `None` if the page wasn't crawled before
+    revision: Option<Arc<Revision>>,
+}
+
+// NOTE: the depth is left out on purpose so that a URL which is found on different levels is
+// only crawled once
+impl<T> PartialEq for Job<T>
+where
+    T: Fetch + PartialEq,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.fetcher == other.fetcher && self.url == other.url
+    }
+}
+
+impl<T> Eq for Job<T> where T: Fetch + Eq {}
+
+impl<T> Hash for Job<T>
+where
+    T: Fetch + Hash,
+{
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.fetcher.hash(state);
+        self.url.hash(state);
+    }
 }
 
 impl<T> Job<T>
 where
     T: Fetch,
 {
-    pub fn new(fetcher: Arc<T>, url: Url) -> Option<Self> {
-        let url_str = url.as_str();
-        let blacklisted_content_type = BLACKLIST_CONTENT_TYPES
-            .iter()
-            .any(|&t| url_str.contains(format!(".{}", t).as_str()));
-        let blacklisted_domain = BLACKLIST_DOMAINS
-            .iter()
-            .any(|&domain| url_str.contains(format!("{}.", domain).as_str()));
-        if blacklisted_content_type || blacklisted_domain {
+    pub fn new(
+        fetcher: Arc<T>,
+        url: Url,
+        depth: usize,
+        scheme_policy: &SchemePolicy,
+    ) -> Option<Self> {
+        // URLs like `mailto:` or `javascript:` can't be fetched
+        if !scheme_policy.admits(&url) {
             return None;
         }
-        Some(Job { fetcher, url })
+        Some(Job {
+            fetcher,
+            url,
+            depth,
+            anchor_text: None,
+            retries: 0,
+            revision: None,
+        })
+    }
+
+    // the text of the link the URL was found in
+    pub fn set_anchor_text(&mut self, anchor_text: Option<String>) {
+        self.anchor_text = anchor_text;
     }
 
     pub fn get_url(&self) -> Url {
         self.url.to_owned()
     }
 
-    pub fn fetch(&self) -> shared::Result<(String, Vec<u8>)> {
-        self.fetcher.fetch(self.url.as_str())
+    pub fn get_depth(&self) -> usize {
+        self.depth
+    }
+
+    pub fn get_anchor_text(&self) -> Option<&str> {
+        self.anchor_text.as_deref()
+    }
+
+    pub fn set_retries(&mut self, retries: usize) {
+        self.retries = retries;
+    }
+
+    pub fn get_retries(&self) -> usize {
+        self.retries
+    }
+
+    // what's known about the page from a previous crawl
+    pub fn set_revision(&mut self, revision: Option<Arc<Revision>>) {
+        self.revision = revision;
+    }
+
+    pub fn get_revision(&self) -> Option<&Revision> {
+        self.revision.as_deref()
+    }
+
+    // pages which were crawled before are only fetched again if they changed
+    pub fn fetch(&self) -> shared::Result<Response> {
+        match &self.revision {
+            Some(revision) => self.fetcher.fetch_if_changed(&self.url, revision),
+            None => self.fetcher.fetch_response(&self.url),
+        }
     }
 }
 
 #[cfg(test)]
 mod job_tests {
-    use crate::job::{Job, BLACKLIST_CONTENT_TYPES, BLACKLIST_DOMAINS};
+    use crate::crawling::SchemePolicy;
+    use crate::filter::{UrlFilter, EXCLUDED_EXTENSIONS};
+    use crate::job::{Job, Spill};
     use crate::shared;
     use crate::traits::Fetch;
     use reqwest::Url;
     use std::sync::Arc;
 
+    #[derive(Default)]
     struct MockFetcher;
-    impl Default for MockFetcher {
-        fn default() -> Self {
-            MockFetcher {}
-        }
-    }
     impl Fetch for MockFetcher {
         fn fetch(&self, _url: &str) -> shared::Result<(String, Vec<u8>)> {
             let content_type = "text/html".to_string();
@@ -77,9 +147,14 @@ mod job_tests {
         }
     }
 
+    // applies the default filter first like the crawler does
     fn create_job(url: &str) -> Option<Job<MockFetcher>> {
-        let fetcher = MockFetcher::default();
-        Job::new(Arc::new(fetcher), Url::parse(url).unwrap())
+        let fetcher = MockFetcher;
+        let url = Url::parse(url).unwrap();
+        if !UrlFilter::default().allows(&url) {
+            return None;
+        }
+        Job::new(Arc::new(fetcher), url, 0, &SchemePolicy::default())
     }
 
     #[test]
@@ -91,16 +166,48 @@ mod job_tests {
         assert!(create_job("http://example.com/index.pdf").is_some());
         assert!(create_job("http://example.com/index.ps").is_some());
         assert!(create_job("http://example.com/index.txt").is_some());
-        for t in BLACKLIST_CONTENT_TYPES.iter() {
-            let formatted = format!("http://example.com/blacklisted.{}", t);
-            let url = formatted.as_str();
-            assert!(create_job(url).is_none());
-        }
-        for domain in BLACKLIST_DOMAINS.iter() {
-            let formatted = format!("http://{}.com/foo", domain);
+        assert!(create_job("https://example.com/index").is_some());
+        assert!(create_job("mailto:jdoe@example.com").is_none());
+        assert!(create_job("javascript:void(0)").is_none());
+        assert!(create_job("ftp://example.com/file").is_none());
+        for extension in EXCLUDED_EXTENSIONS.iter() {
+            let formatted = format!("http://example.com/excluded.{}", extension);
             let url = formatted.as_str();
             assert!(create_job(url).is_none());
         }
+        assert!(create_job("http://www.google.com/foo").is_none());
+        assert!(create_job("http://facebook.com/foo").is_none());
+        // only the extension of the path and the whole host are checked
+        assert!(create_job("http://example.com/docs.jsonnet/").is_some());
+        assert!(create_job("http://mybing.org/").is_some());
+    }
+
+    #[test]
+    fn job_depth() {
+        let fetcher = Arc::new(MockFetcher);
+        let url = Url::parse("http://example.com/foo").unwrap();
+
+        let scheme_policy = SchemePolicy::default();
+
+        let seed = Job::new(fetcher.clone(), url.clone(), 0, &scheme_policy).unwrap();
+        let child = Job::new(fetcher, url, seed.get_depth() + 1, &scheme_policy).unwrap();
+
+        assert_eq!(seed.get_depth(), 0);
+        assert_eq!(child.get_depth(), 1);
+    }
+
+    #[test]
+    fn job_spill_line() {
+        let mut job = create_job("http://example.com/foo").unwrap();
+        job.set_anchor_text(Some("Foo Bar".to_string()));
+        job.set_retries(2);
+
+        let line = job.to_line();
+        assert_eq!(line, "0\t2\thttp://example.com/foo\tFoo Bar");
+        let restored = Job::from_line(&line, &Arc::new(MockFetcher)).unwrap();
+        assert_eq!(restored.get_retries(), 2);
+        assert_eq!(restored.get_anchor_text(), Some("Foo Bar"));
+        assert!(Job::from_line("0\thttp://example.com/foo", &Arc::new(MockFetcher)).is_none());
     }
 
     #[test]
@@ -110,82 +217,382 @@ mod job_tests {
         let content_type = "text/html".to_string();
         let content: Vec<u8> = vec![1, 2, 3, 4];
 
-        assert_eq!(job.fetch().unwrap(), (content_type, content));
+        let response = job.fetch().unwrap();
+        assert_eq!(response.url, job.get_url());
+        assert_eq!(response.status, 200);
+        assert_eq!(
+            (response.content_type, response.content),
+            (content_type, content)
+        );
+    }
+}
+
+// values which can be written to a single line of text and read back later on. The context holds
+// everything which can't be written as text (e.g. the fetcher of a job)
+pub trait Spill: Sized {
+    type Context;
+
+    fn to_line(&self) -> String;
+    fn from_line(line: &str, context: &Self::Context) -> Option<Self>;
+    // identifies the value in the seen set, so it has to be the same for values which are
+    // considered duplicates
+    fn get_key(&self) -> &str;
+}
+
+impl<T> Spill for Job<T>
+where
+    T: Fetch,
+{
+    type Context = Arc<T>;
+
+    // the retries are kept so that a job which always fails isn't retried forever after it was
+    // spilled or restored from a checkpoint.
+    // NOTE: the anchor text never contains tabs since its whitespace is collapsed
+    fn to_line(&self) -> String {
+        let line = format!("{}\t{}\t{}", self.depth, self.retries, self.url);
+        match &self.anchor_text {
+            Some(anchor_text) => format!("{}\t{}", line, anchor_text),
+            None => line,
+        }
+    }
+
+    fn from_line(line: &str, fetcher: &Arc<T>) -> Option<Self> {
+        let mut parts = line.splitn(4, '\t');
+        let depth = parts.next()?.parse::<usize>().ok()?;
+        let retries = parts.next()?.parse::<usize>().ok()?;
+        let url = Url::parse(parts.next()?).ok()?;
+        // the job already passed the scheme policy before it was spilled
+        Some(Job {
+            fetcher: fetcher.clone(),
+            url,
+            depth,
+            anchor_text: parts.next().map(|text| text.to_string()),
+            retries,
+            revision: None,
+        })
+    }
+
+    // the canonical URL. The depth and the fetcher don't make a job a different one
+    fn get_key(&self) -> &str {
+        self.url.as_str()
+    }
+}
+
+// a FIFO of lines on disk which is removed once it's dropped
+struct SpillFile {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    reader: BufReader<File>,
+    len: usize,
+}
+
+impl SpillFile {
+    fn create() -> io::Result<Self> {
+        let id = SPILL_FILE_ID.fetch_add(1, Ordering::SeqCst);
+        let name = format!("crawler-queue-{}-{}", process::id(), id);
+        let path = env::temp_dir().join(name);
+        File::create(&path)?;
+        let writer = BufWriter::new(OpenOptions::new().append(true).open(&path)?);
+        let reader = BufReader::new(File::open(&path)?);
+        Ok(SpillFile {
+            path,
+            writer,
+            reader,
+            len: 0,
+        })
+    }
+
+    fn push(&mut self, line: &str) -> io::Result<()> {
+        writeln!(self.writer, "{}", line)?;
+        self.len += 1;
+        Ok(())
+    }
+
+    fn pop(&mut self, max: usize) -> io::Result<Vec<String>> {
+        self.writer.flush()?;
+        let mut lines = vec![];
+        let mut line = String::new();
+        while lines.len() < max && self.reader.read_line(&mut line)? > 0 {
+            lines.push(line.trim_end().to_string());
+            line.clear();
+        }
+        self.len -= lines.len();
+        // start over once everything was read so that the file doesn't grow forever
+        if self.len == 0 {
+            self.writer.get_ref().set_len(0)?;
+            self.reader.seek(SeekFrom::Start(0))?;
+        }
+        Ok(lines)
+    }
+
+    // returns the lines which weren't read yet without consuming them
+    fn remaining(&mut self) -> io::Result<impl Iterator<Item = io::Result<String>>> {
+        self.writer.flush()?;
+        let mut file = File::open(&self.path)?;
+        file.seek(SeekFrom::Start(self.reader.stream_position()?))?;
+        Ok(BufReader::new(file).lines().take(self.len))
     }
 }
 
-pub struct Queue<T> {
-    queue: VecDeque<T>,
-    seen: HashSet<T>,
+impl Drop for SpillFile {
+    fn drop(&mut self) {
+        fs::remove_file(&self.path).unwrap_or(());
+    }
+}
+
+// every value is remembered from the moment it's enqueued so that checking for duplicates doesn't
+// have to scan the queue and values which were dequeued long ago are never crawled again. Values
+// are dequeued by their score. Values which don't fit into the buffer are spilled to disk and read
+// back once the buffer ran empty, so the order is only exact as long as the buffer isn't exceeded
+pub struct Queue<T>
+where
+    T: Spill,
+{
+    // ordered by the highest score first and the order in which the values were pushed second
+    queue: BTreeMap<(Reverse<i64>, u64), T>,
+    pushed: u64,
+    scorer: Box<dyn Score<T>>,
+    seen: SeenFilter,
     buffer: usize,
+    spill: Option<SpillFile>,
+    context: T::Context,
 }
 
 impl<T> Queue<T>
 where
-    T: Eq + Hash + Clone,
+    T: Eq + Clone + Spill,
 {
-    pub fn new(buffer: usize) -> Self {
+    pub fn new(buffer: usize, context: T::Context) -> Self {
         Queue {
-            queue: VecDeque::<T>::new(),
-            seen: HashSet::<T>::new(),
-            buffer,
+            queue: BTreeMap::new(),
+            pushed: 0,
+            scorer: Box::new(BreadthFirst),
+            seen: SeenFilter::new(buffer),
+            buffer: buffer.max(1),
+            spill: None,
+            context,
         }
     }
 
-    pub fn enqueue(&mut self, value: T) {
-        // remove the oldest element if the queue exceeds the buffer size
-        if self.queue.len() == self.buffer {
-            self.queue.pop_front();
-        }
-        // enqueue the new value
-        if !self.seen.contains(&value) && !self.queue.contains(&value) {
-            self.queue.push_back(value);
+    pub fn set_scorer(&mut self, scorer: Box<dyn Score<T>>) {
+        self.scorer = scorer;
+    }
+
+    // NOTE: this has to be called before anything is enqueued since the seen set starts over
+    pub fn set_buffer(&mut self, buffer: usize) {
+        self.seen = SeenFilter::new(buffer);
+        self.buffer = buffer.max(1);
+    }
+
+    // returns `false` and ignores the value if it was already enqueued once
+    pub fn enqueue(&mut self, value: T) -> bool {
+        if !self.seen.insert(fingerprint(value.get_key())) {
+            return false;
         }
+        let score = self.scorer.score(&value);
+        self.push(score, value);
+        true
+    }
+
+    // enqueues a value again although it was seen before, e.g. to retry it
+    pub fn requeue(&mut self, value: T) {
+        let score = self.scorer.score(&value);
+        self.push(score, value);
+    }
+
+    // remembers a value without enqueueing it. Returns `false` if it was already seen
+    pub fn mark_seen(&mut self, value: &T) -> bool {
+        self.seen.insert(fingerprint(value.get_key()))
     }
 
     pub fn dequeue(&mut self) -> Option<T> {
-        // clear the whole set if we exceed the buffer size
-        if self.seen.len() == self.buffer {
-            self.seen.clear();
+        if self.queue.is_empty() {
+            self.page_in();
         }
-        // dequeue the item
-        if let Some(value) = self.queue.pop_front() {
-            // mark as seen when dequeuing it
-            self.seen.insert(value.clone());
-            return Some(value);
+        self.queue.pop_first().map(|(_, value)| value)
+    }
+
+    // dequeues the best value which satisfies the predicate, e.g. a job whose host is ready.
+    // NOTE: only the best values in the buffer are looked at, so worse ones have to wait
+    pub fn dequeue_where<P>(&mut self, mut predicate: P) -> Option<T>
+    where
+        P: FnMut(&T) -> bool,
+    {
+        if self.queue.is_empty() {
+            self.page_in();
         }
-        None
+        let key = *self
+            .queue
+            .iter()
+            .take(MAX_SCANNED)
+            .find(|(_, value)| predicate(value))?
+            .0;
+        self.queue.remove(&key)
+    }
+
+    pub fn has_seen(&self, value: &T) -> bool {
+        self.seen.contains(fingerprint(value.get_key()))
+    }
+
+    pub fn len(&self) -> usize {
+        self.queue.len() + self.spilled()
     }
 
     pub fn is_empty(&self) -> bool {
-        self.queue.is_empty()
+        self.len() == 0
+    }
+
+    // writes the given values (e.g. the ones which were dequeued but not processed yet), all
+    // pending values and the seen set line by line. The given values come first once restored
+    pub fn write_to<'a, W, I>(&mut self, writer: &mut W, values: I) -> io::Result<()>
+    where
+        W: Write,
+        I: IntoIterator<Item = &'a T>,
+        T: 'a,
+    {
+        for value in values {
+            writeln!(writer, "pending {}\t{}", i64::MAX, value.to_line())?;
+        }
+        for ((Reverse(score), _), value) in self.queue.iter() {
+            writeln!(writer, "pending {}\t{}", score, value.to_line())?;
+        }
+        if let Some(spill) = self.spill.as_mut() {
+            for line in spill.remaining()? {
+                writeln!(writer, "pending {}", line?)?;
+            }
+        }
+        for line in self.scorer.get_state() {
+            writeln!(writer, "scorer {}", line)?;
+        }
+        self.seen.write_to(writer)
+    }
+
+    // restores a line which was written by `write_to`.
+    // NOTE: the state of the scorer is only restored if it's set before
+    pub fn read_line(&mut self, line: &str) -> Option<()> {
+        if let Some(line) = line.strip_prefix("pending ") {
+            let (score, value) = self.parse_line(line)?;
+            self.push(score, value);
+            return Some(());
+        }
+        match line.strip_prefix("scorer ") {
+            Some(line) => self.scorer.restore_state(line),
+            None => self.seen.read_line(line),
+        }
+    }
+
+    fn push(&mut self, score: i64, value: T) {
+        let key = (Reverse(score), self.pushed);
+        self.pushed += 1;
+        if self.queue.len() < self.buffer && self.spilled() == 0 {
+            self.queue.insert(key, value);
+            return;
+        }
+        // values which are worse than everything in the buffer wait on disk. Otherwise the worst
+        // value in the buffer makes room if it's full
+        let (score, value) = match self.queue.keys().next_back() {
+            Some(worst) if key < *worst => {
+                self.queue.insert(key, value);
+                if self.queue.len() <= self.buffer {
+                    return;
+                }
+                let ((Reverse(score), _), value) = self.queue.pop_last().unwrap();
+                (score, value)
+            }
+            _ => (score, value),
+        };
+        if let Err(err) = self.spill(score, &value) {
+            // rather exceed the buffer than lose the value
+            loge!(format!("Failed to spill queue to disk: {}", err));
+            let key = (Reverse(score), self.pushed);
+            self.pushed += 1;
+            self.queue.insert(key, value);
+        }
+    }
+
+    fn parse_line(&self, line: &str) -> Option<(i64, T)> {
+        let (score, line) = line.split_once('\t')?;
+        let score = score.parse::<i64>().ok()?;
+        let value = T::from_line(line, &self.context)?;
+        Some((score, value))
+    }
+
+    fn spilled(&self) -> usize {
+        self.spill.as_ref().map_or(0, |spill| spill.len)
+    }
+
+    fn spill(&mut self, score: i64, value: &T) -> io::Result<()> {
+        if self.spill.is_none() {
+            self.spill = Some(SpillFile::create()?);
+        }
+        let line = format!("{}\t{}", score, value.to_line());
+        self.spill.as_mut().unwrap().push(&line)
+    }
+
+    fn page_in(&mut self) {
+        let spill = match self.spill.as_mut() {
+            Some(spill) if spill.len > 0 => spill,
+            _ => return,
+        };
+        let lines = match spill.pop(self.buffer) {
+            Ok(lines) if !lines.is_empty() => lines,
+            // the values can't be read back, e.g. because the file was truncated. They're given up
+            // on so that the queue doesn't claim to have values which it can never return
+            result => {
+                let reason = result
+                    .err()
+                    .map_or("it ended early".to_string(), |err| err.to_string());
+                loge!(format!(
+                    "Failed to read spilled queue from disk, {} values are lost: {}",
+                    spill.len, reason
+                ));
+                self.spill = None;
+                return;
+            }
+        };
+        for line in lines {
+            if let Some((score, value)) = self.parse_line(&line) {
+                let key = (Reverse(score), self.pushed);
+                self.pushed += 1;
+                self.queue.insert(key, value);
+            }
+        }
     }
 }
 
 #[cfg(test)]
 mod queue_tests {
+    use crate::crawling::SchemePolicy;
     use crate::job::{Job, Queue};
+    use crate::priority::{DepthFirst, ShallowestFirst};
     use crate::traits::Fetch;
     use reqwest::Url;
+    use std::fs::File;
+    use std::io::Write;
     use std::sync::Arc;
 
-    #[derive(Eq, PartialEq, Hash, Clone, Debug)]
+    #[derive(Eq, PartialEq, Hash, Clone, Debug, Default)]
     struct MockFetcher;
-    impl Default for MockFetcher {
-        fn default() -> Self {
-            MockFetcher {}
-        }
-    }
     impl Fetch for MockFetcher {}
 
     fn to_job(url: &str) -> Job<MockFetcher> {
-        let fetcher = MockFetcher::default();
-        Job::new(Arc::new(fetcher), Url::parse(url).unwrap()).unwrap()
+        let fetcher = MockFetcher;
+        Job::new(
+            Arc::new(fetcher),
+            Url::parse(url).unwrap(),
+            0,
+            &SchemePolicy::default(),
+        )
+        .unwrap()
+    }
+
+    fn new_queue(buffer: usize) -> Queue<Job<MockFetcher>> {
+        Queue::new(buffer, Arc::new(MockFetcher))
     }
 
     #[test]
     fn queue() {
-        let mut q = Queue::new(10);
+        let mut q = new_queue(10);
 
         let job_1 = to_job("http://example.com/1");
         let job_2 = to_job("http://example.com/1");
@@ -212,58 +619,158 @@ mod queue_tests {
 
     #[test]
     fn queue_is_empty() {
-        let mut q = Queue::new(10);
+        let mut q = new_queue(10);
         let job = to_job("http://example.com");
 
-        assert_eq!(q.is_empty(), true);
+        assert!(q.is_empty());
         q.enqueue(job);
-        assert_eq!(q.is_empty(), false);
+        assert!(!q.is_empty());
         q.dequeue();
-        assert_eq!(q.is_empty(), true);
+        assert!(q.is_empty());
     }
 
     #[test]
     fn queue_contains() {
-        let mut q = Queue::new(10);
+        let mut q = new_queue(10);
         let job = to_job("http://example.com");
 
         q.enqueue(job.clone());
-        assert!(q.queue.contains(&job));
-        assert!(!q.seen.contains(&job));
+        assert!(q.queue.values().any(|value| value == &job));
+        assert!(q.has_seen(&job));
         q.dequeue();
-        assert!(!q.queue.contains(&job));
-        assert!(q.seen.contains(&job));
+        assert!(!q.queue.values().any(|value| value == &job));
+        assert!(q.has_seen(&job));
         // trying to enqueue the same job again
         q.enqueue(job.clone());
-        assert!(!q.queue.contains(&job));
-        assert!(q.seen.contains(&job));
+        assert!(!q.queue.values().any(|value| value == &job));
+        assert!(q.has_seen(&job));
+    }
+
+    #[test]
+    fn queue_requeue() {
+        let mut q = new_queue(10);
+        let mut job = to_job("http://example.com");
+
+        q.enqueue(job.clone());
+        q.dequeue();
+        assert!(!q.enqueue(job.clone()));
+        job.set_retries(1);
+        q.requeue(job.clone());
+        assert!(q.has_seen(&job));
+        assert_eq!(q.dequeue().unwrap().get_retries(), 1);
+        assert_eq!(q.dequeue(), None);
+    }
+
+    #[test]
+    fn queue_dequeue_where() {
+        let mut q = new_queue(10);
+        let job_1 = to_job("http://example.com/1");
+        let job_2 = to_job("http://jdoe.com/2");
+        let job_3 = to_job("http://example.com/3");
+
+        q.enqueue(job_1.clone());
+        q.enqueue(job_2.clone());
+        q.enqueue(job_3.clone());
+
+        let is_jdoe = |job: &Job<MockFetcher>| job.get_url().host_str() == Some("jdoe.com");
+        assert_eq!(q.dequeue_where(is_jdoe), Some(job_2));
+        assert_eq!(q.dequeue_where(is_jdoe), None);
+        assert_eq!(q.dequeue(), Some(job_1));
+        assert_eq!(q.dequeue(), Some(job_3));
+    }
+
+    #[test]
+    fn queue_ignores_depth() {
+        let mut q = new_queue(10);
+        let fetcher = Arc::new(MockFetcher);
+        let url = Url::parse("http://example.com").unwrap();
+
+        q.enqueue(Job::new(fetcher.clone(), url.clone(), 0, &SchemePolicy::default()).unwrap());
+        q.enqueue(Job::new(fetcher, url, 1, &SchemePolicy::default()).unwrap());
+
+        assert_eq!(q.dequeue().unwrap().get_depth(), 0);
+        assert_eq!(q.dequeue(), None);
     }
 
     #[test]
     fn queue_buffer_queue() {
-        let mut q = Queue::new(3);
+        let mut q = new_queue(3);
 
         let job_1 = to_job("http://example.com/1");
         let job_2 = to_job("http://example.com/2");
         let job_3 = to_job("http://example.com/3");
         let job_4 = to_job("http://example.com/4");
         let job_5 = to_job("http://example.com/5");
+        let job_6 = to_job("http://example.com/6");
 
         q.enqueue(job_1.clone());
         q.enqueue(job_2.clone());
         q.enqueue(job_3.clone());
         q.enqueue(job_4.clone());
         q.enqueue(job_5.clone());
+        assert_eq!(q.queue.len(), 3);
+        assert_eq!(q.spilled(), 2);
+        assert_eq!(q.len(), 5);
 
+        assert_eq!(q.dequeue(), Some(job_1));
+        // jobs wait behind the spilled ones even if there's room in the buffer
+        q.enqueue(job_6.clone());
+        assert_eq!(q.spilled(), 3);
+        assert_eq!(q.dequeue(), Some(job_2));
         assert_eq!(q.dequeue(), Some(job_3));
         assert_eq!(q.dequeue(), Some(job_4));
         assert_eq!(q.dequeue(), Some(job_5));
+        assert_eq!(q.dequeue(), Some(job_6));
         assert_eq!(q.dequeue(), None);
+        assert!(q.is_empty());
+    }
+
+    #[test]
+    fn queue_spill_file() {
+        let mut q = new_queue(1);
+        let fetcher = Arc::new(MockFetcher);
+        let url = Url::parse("http://example.com/2").unwrap();
+
+        let mut job = Job::new(fetcher, url.clone(), 3, &SchemePolicy::default()).unwrap();
+        job.set_anchor_text(Some("Example".to_string()));
+        q.enqueue(to_job("http://example.com/1"));
+        q.enqueue(job);
+        let path = q.spill.as_ref().unwrap().path.clone();
+        assert!(path.exists());
+
+        q.dequeue();
+        let job = q.dequeue().unwrap();
+        assert_eq!(job.get_url(), url);
+        assert_eq!(job.get_depth(), 3);
+        assert_eq!(job.get_anchor_text(), Some("Example"));
+        // the file is reused once it was read completely
+        assert_eq!(path.metadata().unwrap().len(), 0);
+
+        drop(q);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn queue_spill_file_lost() {
+        let mut q = new_queue(1);
+        q.enqueue(to_job("http://example.com/1"));
+        q.enqueue(to_job("http://example.com/2"));
+        q.enqueue(to_job("http://example.com/3"));
+        let path = q.spill.as_ref().unwrap().path.clone();
+        q.spill.as_mut().unwrap().writer.flush().unwrap();
+        File::create(&path).unwrap();
+
+        // the spilled values are given up on instead of keeping the queue from ever running empty
+        assert!(q.dequeue().is_some());
+        assert!(!q.is_empty());
+        assert_eq!(q.dequeue(), None);
+        assert!(q.is_empty());
+        assert!(!path.exists());
     }
 
     #[test]
     fn queue_buffer_seen() {
-        let mut q = Queue::new(2);
+        let mut q = new_queue(2);
 
         let job_1 = to_job("http://example.com/1");
         let job_2 = to_job("http://example.com/2");
@@ -274,15 +781,123 @@ mod queue_tests {
         q.enqueue(job_2.clone());
         q.dequeue();
         q.dequeue();
-        assert_eq!(q.seen.len(), 2);
-        assert!(q.seen.contains(&job_1));
-        assert!(q.seen.contains(&job_2));
         q.enqueue(job_3.clone());
         q.enqueue(job_4.clone());
         q.dequeue();
         q.dequeue();
-        assert_eq!(q.seen.len(), 2);
-        assert!(q.seen.contains(&job_3));
-        assert!(q.seen.contains(&job_4));
+        assert_eq!(q.seen.len(), 4);
+        assert!(q.has_seen(&job_1));
+        assert!(q.has_seen(&job_2));
+        assert!(q.has_seen(&job_3));
+        assert!(q.has_seen(&job_4));
+        // jobs which were seen before the buffer filled up are still skipped
+        q.enqueue(job_1);
+        q.enqueue(job_2);
+        assert_eq!(q.dequeue(), None);
+    }
+
+    #[test]
+    fn queue_write_and_read() {
+        let mut q = new_queue(2);
+        let job_1 = to_job("http://example.com/1");
+        let job_2 = to_job("http://example.com/2");
+        let job_3 = to_job("http://example.com/3");
+        let job_4 = to_job("http://example.com/4");
+
+        q.enqueue(job_1.clone());
+        q.enqueue(job_2.clone());
+        q.enqueue(job_3.clone());
+        q.enqueue(job_4.clone());
+        let dequeued = q.dequeue().unwrap();
+        let mut state: Vec<u8> = vec![];
+        q.write_to(&mut state, vec![&dequeued]).unwrap();
+        // writing the queue doesn't consume the spilled jobs
+        assert_eq!(q.len(), 3);
+
+        let mut restored = new_queue(2);
+        for line in String::from_utf8(state).unwrap().lines() {
+            assert_eq!(restored.read_line(line), Some(()));
+        }
+        assert!(restored.has_seen(&job_4));
+        assert_eq!(restored.dequeue(), Some(job_1));
+        assert_eq!(restored.dequeue(), Some(job_2));
+        assert_eq!(restored.dequeue(), Some(job_3));
+        assert_eq!(restored.dequeue(), Some(job_4.clone()));
+        assert_eq!(restored.dequeue(), None);
+        restored.enqueue(job_4);
+        assert!(restored.is_empty());
+    }
+
+    #[test]
+    fn queue_scorer() {
+        let mut q = new_queue(10);
+        q.set_scorer(Box::new(DepthFirst::default()));
+        let job_1 = to_job("http://example.com/1");
+        let job_2 = to_job("http://example.com/2");
+        let job_3 = to_job("http://example.com/3");
+
+        q.enqueue(job_1.clone());
+        q.enqueue(job_2.clone());
+        assert_eq!(q.dequeue(), Some(job_2));
+        q.enqueue(job_3.clone());
+        assert_eq!(q.dequeue(), Some(job_3));
+        assert_eq!(q.dequeue(), Some(job_1));
+    }
+
+    #[test]
+    fn queue_scorer_write_and_read() {
+        let mut q = new_queue(10);
+        q.set_scorer(Box::new(DepthFirst::default()));
+        let job_1 = to_job("http://example.com/1");
+        let job_2 = to_job("http://example.com/2");
+        let job_3 = to_job("http://example.com/3");
+
+        q.enqueue(job_1.clone());
+        q.enqueue(job_2.clone());
+        let mut state: Vec<u8> = vec![];
+        q.write_to(&mut state, vec![]).unwrap();
+
+        // the page found after resuming is still the one found last
+        let mut restored = new_queue(10);
+        restored.set_scorer(Box::new(DepthFirst::default()));
+        for line in String::from_utf8(state).unwrap().lines() {
+            assert_eq!(restored.read_line(line), Some(()));
+        }
+        restored.enqueue(job_3.clone());
+        assert_eq!(restored.dequeue(), Some(job_3));
+        assert_eq!(restored.dequeue(), Some(job_2));
+        assert_eq!(restored.dequeue(), Some(job_1));
+    }
+
+    #[test]
+    fn queue_scorer_spill() {
+        let mut q = new_queue(2);
+        q.set_scorer(Box::new(ShallowestFirst));
+        let fetcher = Arc::new(MockFetcher);
+        let to_job = |url: &str, depth: usize| {
+            Job::new(
+                fetcher.clone(),
+                Url::parse(url).unwrap(),
+                depth,
+                &SchemePolicy::default(),
+            )
+            .unwrap()
+        };
+        let job_1 = to_job("http://example.com/1", 2);
+        let job_2 = to_job("http://example.com/2", 2);
+        let job_3 = to_job("http://example.com/3", 1);
+        let job_4 = to_job("http://example.com/4", 3);
+
+        q.enqueue(job_1.clone());
+        q.enqueue(job_2.clone());
+        // the worst job in the buffer makes room for a better one
+        q.enqueue(job_3.clone());
+        q.enqueue(job_4.clone());
+        assert_eq!(q.spilled(), 2);
+        assert_eq!(q.dequeue(), Some(job_3));
+        assert_eq!(q.dequeue(), Some(job_1));
+        assert_eq!(q.dequeue(), Some(job_2));
+        assert_eq!(q.dequeue(), Some(job_4));
+        assert_eq!(q.dequeue(), None);
     }
 }
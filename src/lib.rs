@@ -1,15 +1,29 @@
 #[macro_use]
 extern crate lazy_static;
 extern crate chrono;
+extern crate encoding_rs;
 extern crate regex;
 extern crate reqwest;
 
 #[macro_use]
 pub mod logging;
 pub mod args;
+pub mod canonical;
+pub mod charset;
+pub mod checkpoint;
 pub mod crawler;
 pub mod crawling;
 pub mod error;
+pub mod filter;
+pub mod history;
+pub mod html;
 pub mod job;
+pub mod politeness;
+pub mod priority;
+pub mod response;
+pub mod retry;
+pub mod robots;
+pub mod scope;
+pub mod seen;
 pub mod shared;
 pub mod traits;
@@ -0,0 +1,162 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+struct Host {
+    in_flight: usize,
+    next_request: Instant,
+}
+
+// keeps track of the requests to every host. The jobs themselves stay in the queue until their
+// host is ready so that a better job which is found in the meantime is still crawled first
+pub struct HostScheduler {
+    delay: Duration,
+    max_per_host: Option<usize>,
+    hosts: HashMap<String, Host>,
+}
+
+impl HostScheduler {
+    pub fn new(delay: Duration, max_per_host: Option<usize>) -> Self {
+        HostScheduler {
+            delay,
+            max_per_host,
+            hosts: HashMap::new(),
+        }
+    }
+
+    pub fn is_ready(&self, host: &str, now: Instant) -> bool {
+        match self.hosts.get(host) {
+            Some(state) => self.is_below_limit(state) && state.next_request <= now,
+            None => true,
+        }
+    }
+
+    // the crawl delay of a host (e.g. from its `robots.txt`) is used if it's longer than ours
+    pub fn start(&mut self, host: &str, now: Instant, crawl_delay: Option<Duration>) {
+        let delay = crawl_delay.map_or(self.delay, |crawl_delay| crawl_delay.max(self.delay));
+        let state = self.hosts.entry(host.to_string()).or_insert(Host {
+            in_flight: 0,
+            next_request: now,
+        });
+        state.in_flight += 1;
+        state.next_request = now + delay;
+    }
+
+    // a request which was sent without being scheduled, e.g. for `robots.txt`, still delays the
+    // next one to the host
+    pub fn record(&mut self, host: &str, now: Instant, crawl_delay: Option<Duration>) {
+        self.start(host, now, crawl_delay);
+        self.finish(host, now);
+    }
+
+    pub fn finish(&mut self, host: &str, now: Instant) {
+        if let Some(state) = self.hosts.get_mut(host) {
+            state.in_flight = state.in_flight.saturating_sub(1);
+            // hosts which can be requested right away don't need to be remembered
+            if state.in_flight == 0 && state.next_request <= now {
+                self.hosts.remove(host);
+            }
+        }
+    }
+
+    // the earliest point in time after `now` at which a host becomes ready because of its delay.
+    // Hosts which wait for a request to finish aren't considered
+    pub fn next_ready_at(&self, now: Instant) -> Option<Instant> {
+        self.hosts
+            .values()
+            .filter(|state| self.is_below_limit(state) && state.next_request > now)
+            .map(|state| state.next_request)
+            .min()
+    }
+
+    fn is_below_limit(&self, state: &Host) -> bool {
+        self.max_per_host
+            .is_none_or(|max_per_host| state.in_flight < max_per_host)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::politeness::HostScheduler;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn host_scheduler_delay() {
+        let mut scheduler = HostScheduler::new(Duration::from_secs(1), None);
+        let now = Instant::now();
+
+        assert!(scheduler.is_ready("example.com", now));
+        scheduler.start("example.com", now, None);
+        scheduler.finish("example.com", now);
+        assert!(!scheduler.is_ready("example.com", now));
+        assert!(scheduler.is_ready("jdoe.com", now));
+        assert!(scheduler.is_ready("example.com", now + Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn host_scheduler_record() {
+        let mut scheduler = HostScheduler::new(Duration::from_secs(1), Some(1));
+        let now = Instant::now();
+
+        scheduler.record("example.com", now, Some(Duration::from_secs(2)));
+        assert!(!scheduler.is_ready("example.com", now + Duration::from_secs(1)));
+        assert!(scheduler.is_ready("example.com", now + Duration::from_secs(2)));
+    }
+
+    #[test]
+    fn host_scheduler_crawl_delay() {
+        let mut scheduler = HostScheduler::new(Duration::from_secs(1), None);
+        let now = Instant::now();
+
+        scheduler.start("example.com", now, Some(Duration::from_secs(5)));
+        scheduler.start("jdoe.com", now, Some(Duration::from_millis(10)));
+        assert!(!scheduler.is_ready("example.com", now + Duration::from_secs(1)));
+        assert!(scheduler.is_ready("example.com", now + Duration::from_secs(5)));
+        assert!(!scheduler.is_ready("jdoe.com", now + Duration::from_millis(10)));
+        assert!(scheduler.is_ready("jdoe.com", now + Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn host_scheduler_max_per_host() {
+        let mut scheduler = HostScheduler::new(Duration::from_secs(0), Some(2));
+        let now = Instant::now();
+
+        scheduler.start("example.com", now, None);
+        assert!(scheduler.is_ready("example.com", now));
+        scheduler.start("example.com", now, None);
+        assert!(!scheduler.is_ready("example.com", now));
+        scheduler.finish("example.com", now);
+        assert!(scheduler.is_ready("example.com", now));
+    }
+
+    #[test]
+    fn host_scheduler_next_ready_at() {
+        let mut scheduler = HostScheduler::new(Duration::from_secs(1), None);
+        let now = Instant::now();
+        let later = now + Duration::from_secs(1);
+
+        assert_eq!(scheduler.next_ready_at(now), None);
+        scheduler.start("example.com", now, None);
+        scheduler.start("jdoe.com", now, Some(Duration::from_secs(5)));
+        assert_eq!(scheduler.next_ready_at(now), Some(later));
+        assert_eq!(
+            scheduler.next_ready_at(later),
+            Some(now + Duration::from_secs(5))
+        );
+    }
+
+    #[test]
+    fn host_scheduler_next_ready_at_max_per_host() {
+        let mut scheduler = HostScheduler::new(Duration::from_secs(1), Some(1));
+        let now = Instant::now();
+
+        scheduler.start("example.com", now, None);
+
+        // only a finished request can make the host ready again
+        assert_eq!(scheduler.next_ready_at(now), None);
+        scheduler.finish("example.com", now);
+        assert_eq!(
+            scheduler.next_ready_at(now),
+            Some(now + Duration::from_secs(1))
+        );
+    }
+}
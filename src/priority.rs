@@ -0,0 +1,265 @@
+use crate::job::Job;
+use crate::traits::{Fetch, Score};
+use std::collections::HashMap;
+use std::str::FromStr;
+
+// crawls the pages in the order they were found
+pub struct BreadthFirst;
+
+impl<T> Score<T> for BreadthFirst {
+    fn score(&mut self, _value: &T) -> i64 {
+        0
+    }
+}
+
+// crawls the page which was found last first
+#[derive(Default)]
+pub struct DepthFirst {
+    found: i64,
+}
+
+impl<T> Score<T> for DepthFirst {
+    fn score(&mut self, _value: &T) -> i64 {
+        self.found += 1;
+        self.found
+    }
+
+    fn get_state(&self) -> Vec<String> {
+        vec![self.found.to_string()]
+    }
+
+    fn restore_state(&mut self, line: &str) -> Option<()> {
+        self.found = line.parse().ok()?;
+        Some(())
+    }
+}
+
+// crawls the pages which are closest to the starting URL first, even if they were found later on
+pub struct ShallowestFirst;
+
+impl<B> Score<Job<B>> for ShallowestFirst
+where
+    B: Fetch,
+{
+    fn score(&mut self, job: &Job<B>) -> i64 {
+        -(job.get_depth() as i64)
+    }
+}
+
+// takes turns between the hosts so that a single host with lots of links can't hold up the others
+#[derive(Default)]
+pub struct RoundRobin {
+    hosts: HashMap<String, i64>,
+}
+
+impl<B> Score<Job<B>> for RoundRobin
+where
+    B: Fetch,
+{
+    fn score(&mut self, job: &Job<B>) -> i64 {
+        let host = job.get_url().host_str().unwrap_or("").to_string();
+        let found = self.hosts.entry(host).or_insert(0);
+        *found += 1;
+        -*found
+    }
+
+    // a line per host, e.g. `example.com 42`
+    fn get_state(&self) -> Vec<String> {
+        self.hosts
+            .iter()
+            .map(|(host, found)| format!("{} {}", host, found))
+            .collect()
+    }
+
+    fn restore_state(&mut self, line: &str) -> Option<()> {
+        let (host, found) = line.rsplit_once(' ')?;
+        self.hosts.insert(host.to_string(), found.parse().ok()?);
+        Some(())
+    }
+}
+
+// prefers pages whose URL or anchor text contains the keywords. Every matching keyword adds a point
+pub struct KeywordBoost {
+    keywords: Vec<String>,
+}
+
+impl KeywordBoost {
+    pub fn new(keywords: Vec<String>) -> Self {
+        let keywords = keywords
+            .iter()
+            .map(|keyword| keyword.to_lowercase())
+            .collect();
+        KeywordBoost { keywords }
+    }
+}
+
+impl<B> Score<Job<B>> for KeywordBoost
+where
+    B: Fetch,
+{
+    fn score(&mut self, job: &Job<B>) -> i64 {
+        let url = job.get_url().as_str().to_lowercase();
+        let anchor_text = job.get_anchor_text().unwrap_or("").to_lowercase();
+        self.keywords
+            .iter()
+            .filter(|keyword| {
+                url.contains(keyword.as_str()) || anchor_text.contains(keyword.as_str())
+            })
+            .count() as i64
+    }
+}
+
+// the built-in scores which can be picked by name
+#[derive(PartialEq, Debug, Clone, Default)]
+pub enum Priority {
+    #[default]
+    BreadthFirst,
+    DepthFirst,
+    ShallowestFirst,
+    RoundRobin,
+    Keywords(Vec<String>),
+}
+
+impl Priority {
+    pub fn to_score<B>(&self) -> Box<dyn Score<Job<B>>>
+    where
+        B: Fetch,
+    {
+        match self {
+            Priority::BreadthFirst => Box::new(BreadthFirst),
+            Priority::DepthFirst => Box::new(DepthFirst::default()),
+            Priority::ShallowestFirst => Box::new(ShallowestFirst),
+            Priority::RoundRobin => Box::new(RoundRobin::default()),
+            Priority::Keywords(keywords) => Box::new(KeywordBoost::new(keywords.clone())),
+        }
+    }
+}
+
+impl FromStr for Priority {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "bfs" => Ok(Priority::BreadthFirst),
+            "dfs" => Ok(Priority::DepthFirst),
+            "shallowest" => Ok(Priority::ShallowestFirst),
+            "round-robin" => Ok(Priority::RoundRobin),
+            _ if value.starts_with("keywords:") => {
+                let keywords: Vec<String> = value["keywords:".len()..]
+                    .split(',')
+                    .filter(|keyword| !keyword.is_empty())
+                    .map(|keyword| keyword.to_string())
+                    .collect();
+                if keywords.is_empty() {
+                    return Err(format!("No keywords given in priority \"{}\"", value));
+                }
+                Ok(Priority::Keywords(keywords))
+            }
+            _ => Err(format!("Unknown priority \"{}\"", value)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::crawling::SchemePolicy;
+    use crate::job::Job;
+    use crate::priority::{
+        BreadthFirst, DepthFirst, KeywordBoost, Priority, RoundRobin, ShallowestFirst,
+    };
+    use crate::traits::{Fetch, Score};
+    use reqwest::Url;
+    use std::sync::Arc;
+
+    struct MockFetcher;
+    impl Fetch for MockFetcher {}
+
+    fn to_job(url: &str, depth: usize) -> Job<MockFetcher> {
+        Job::new(
+            Arc::new(MockFetcher),
+            Url::parse(url).unwrap(),
+            depth,
+            &SchemePolicy::default(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn priority_breadth_and_depth_first() {
+        let job_1 = to_job("http://example.com/1", 0);
+        let job_2 = to_job("http://example.com/2", 1);
+
+        assert_eq!(BreadthFirst.score(&job_1), BreadthFirst.score(&job_2));
+        let mut depth_first = DepthFirst::default();
+        assert!(depth_first.score(&job_1) < depth_first.score(&job_2));
+        let mut shallowest_first = ShallowestFirst;
+        assert!(shallowest_first.score(&job_1) > shallowest_first.score(&job_2));
+    }
+
+    #[test]
+    fn priority_round_robin() {
+        let mut round_robin = RoundRobin::default();
+
+        let first = round_robin.score(&to_job("http://example.com/1", 1));
+        let second = round_robin.score(&to_job("http://example.com/2", 1));
+        assert!(first > second);
+        assert_eq!(round_robin.score(&to_job("http://jdoe.com/1", 1)), first);
+    }
+
+    // a scorer of the same kind which continues where the given one left off
+    fn restore<S>(scorer: &S) -> S
+    where
+        S: Score<Job<MockFetcher>> + Default,
+    {
+        let mut restored = S::default();
+        for line in scorer.get_state() {
+            restored.restore_state(&line).unwrap();
+        }
+        restored
+    }
+
+    #[test]
+    fn priority_restore_state() {
+        let job = to_job("http://example.com/1", 1);
+
+        let mut depth_first = DepthFirst::default();
+        let score = depth_first.score(&job);
+        assert!(restore(&depth_first).score(&job) > score);
+
+        let mut round_robin = RoundRobin::default();
+        let score = round_robin.score(&job);
+        let mut restored = restore(&round_robin);
+        assert!(restored.score(&job) < score);
+        assert_eq!(restored.score(&to_job("http://jdoe.com/1", 1)), score);
+        let invalid = Score::<Job<MockFetcher>>::restore_state(&mut restored, "example.com");
+        assert_eq!(invalid, None);
+    }
+
+    #[test]
+    fn priority_keyword_boost() {
+        let mut boost = KeywordBoost::new(vec!["Rust".to_string(), "crawler".to_string()]);
+        let mut job = to_job("http://example.com/rust", 1);
+
+        assert_eq!(boost.score(&to_job("http://example.com/go", 1)), 0);
+        assert_eq!(boost.score(&job), 1);
+        job.set_anchor_text(Some("A web Crawler".to_string()));
+        assert_eq!(boost.score(&job), 2);
+    }
+
+    #[test]
+    fn priority_from_str() {
+        assert_eq!("bfs".parse(), Ok(Priority::BreadthFirst));
+        assert_eq!("dfs".parse(), Ok(Priority::DepthFirst));
+        assert_eq!("shallowest".parse(), Ok(Priority::ShallowestFirst));
+        assert_eq!("round-robin".parse(), Ok(Priority::RoundRobin));
+        assert_eq!(
+            "keywords:rust,crawler".parse(),
+            Ok(Priority::Keywords(vec![
+                "rust".to_string(),
+                "crawler".to_string()
+            ]))
+        );
+        assert!("keywords:".parse::<Priority>().is_err());
+        assert!("foo".parse::<Priority>().is_err());
+    }
+}
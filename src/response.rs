@@ -0,0 +1,381 @@
+use crate::charset;
+use crate::error::CrawlerError::{self, ContentTypeError, IoError, RequestError, SizeError};
+use crate::shared;
+use encoding_rs::Encoding;
+use reqwest::header::{HeaderMap, CONTENT_TYPE, LOCATION};
+use reqwest::{Client, RedirectPolicy, StatusCode, Url};
+use std::borrow::Cow;
+use std::io::{self, ErrorKind, Read};
+use std::time::{Duration, Instant};
+
+pub static MAX_REDIRECTS: usize = 10;
+// 50 MB
+pub static MAX_SIZE: usize = 50 * 1024 * 1024;
+
+lazy_static! {
+    // redirects are followed by hand so that every hop is known
+    pub static ref CLIENT: Client = Client::builder()
+        .redirect(RedirectPolicy::none())
+        .build()
+        .unwrap();
+}
+
+// the maximum size of the content of a response. Larger responses are either truncated or aborted
+#[derive(PartialEq, Eq, Hash, Debug, Clone)]
+pub struct SizeLimits {
+    // `None` if there's no limit
+    pub max_size: Option<usize>,
+    // limits for content types which contain the given value, e.g. `("html", 5_000_000)`. The first
+    // matching one is used instead of `max_size`
+    pub by_content_type: Vec<(String, usize)>,
+    // keeps the start of larger responses instead of failing
+    pub truncate: bool,
+}
+
+impl Default for SizeLimits {
+    fn default() -> Self {
+        SizeLimits {
+            max_size: Some(MAX_SIZE),
+            by_content_type: vec![],
+            truncate: false,
+        }
+    }
+}
+
+impl SizeLimits {
+    pub fn get_limit(&self, content_type: &str) -> Option<usize> {
+        self.by_content_type
+            .iter()
+            .find(|(kind, _)| content_type.contains(kind.as_str()))
+            .map(|(_, limit)| *limit)
+            .or(self.max_size)
+    }
+
+    fn to_error(&self, url: &Url, limit: usize) -> CrawlerError {
+        SizeError(format!(
+            "Content of URL \"{}\" exceeds the limit of {} bytes",
+            url, limit
+        ))
+    }
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct Response {
+    // the URL the content was fetched from after following all redirects
+    pub url: Url,
+    pub status: u16,
+    pub headers: HeaderMap,
+    pub content_type: String,
+    pub content: Vec<u8>,
+    // the content was cut off because it exceeded the size limit
+    pub truncated: bool,
+    // the encoding of HTML pages (see `charset::detect`). `None` for other content and until the
+    // crawler looked at the content
+    pub encoding: Option<&'static Encoding>,
+    // the URLs which were redirected from, starting with the requested one
+    pub redirects: Vec<Url>,
+    // the time it took to fetch the content including all redirects
+    pub duration: Duration,
+}
+
+impl Response {
+    // a successful response without headers or redirects, e.g. for fetchers which only provide the
+    // content type and the content
+    pub fn new(url: Url, content_type: &str, content: Vec<u8>) -> Self {
+        Response {
+            url,
+            status: 200,
+            headers: HeaderMap::new(),
+            content_type: content_type.to_string(),
+            content,
+            truncated: false,
+            encoding: None,
+            redirects: vec![],
+            duration: Duration::from_secs(0),
+        }
+    }
+
+    // the URL which was requested in the first place
+    pub fn get_requested_url(&self) -> &Url {
+        self.redirects.first().unwrap_or(&self.url)
+    }
+
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+
+    // e.g. "503 Service Unavailable"
+    pub fn to_error(&self) -> CrawlerError {
+        match StatusCode::from_u16(self.status) {
+            Ok(status) => RequestError(status.to_string()),
+            Err(_) => RequestError(self.status.to_string()),
+        }
+    }
+
+    // the content decoded into UTF-8 if its encoding is known
+    pub fn get_text(&self) -> Option<Cow<'_, str>> {
+        self.encoding
+            .map(|encoding| charset::decode(&self.content, encoding))
+    }
+
+    // the URL to continue with if this is a redirect which wasn't followed yet
+    pub fn get_location(&self) -> Option<Url> {
+        if !(300..400).contains(&self.status) {
+            return None;
+        }
+        let location = self.headers.get(LOCATION)?.to_str().ok()?;
+        self.url.join(location.trim()).ok()
+    }
+}
+
+// fetches a URL without following redirects. The headers are sent in addition to the default
+// ones of the client, e.g. to make a conditional request. Unsuccessful responses are returned as they are
+// without their content so that e.g. `Retry-After` can be checked. Blacklisted content types,
+// responses which take longer than `timeout` in total and, unless they're truncated, responses
+// which exceed the size limit are errors
+pub fn send(
+    client: &Client,
+    url: &Url,
+    headers: HeaderMap,
+    content_type_blacklist: Option<Vec<&str>>,
+    timeout: Option<Duration>,
+    size_limits: &SizeLimits,
+) -> shared::Result<Response> {
+    let started = Instant::now();
+    let mut resp = client.get(url.clone()).headers(headers).send()?;
+    let content_type = resp
+        .headers()
+        .get(CONTENT_TYPE)
+        .map(|header| header.to_str().unwrap_or("").to_string());
+    let mut response = Response {
+        url: url.clone(),
+        status: resp.status().as_u16(),
+        headers: resp.headers().clone(),
+        content_type: content_type.clone().unwrap_or_default(),
+        content: vec![],
+        truncated: false,
+        encoding: None,
+        redirects: vec![],
+        duration: Duration::from_secs(0),
+    };
+    if !response.is_success() {
+        response.duration = started.elapsed();
+        return Ok(response);
+    }
+
+    let content_type = content_type
+        .ok_or_else(|| ContentTypeError(format!("Invalid Content-Type for URL \"{}\"", url)))?;
+    if content_type_blacklist.is_some_and(|blacklist| {
+        blacklist
+            .into_iter()
+            .any(|blacklisted| content_type.contains(blacklisted))
+    }) {
+        return Err(ContentTypeError(format!(
+            "Blacklisted Content-Type \"{}\" for URL \"{}\"",
+            content_type, url,
+        )));
+    }
+
+    // the announced size is checked before anything is downloaded
+    let limit = size_limits.get_limit(&content_type);
+    if let Some(limit) = limit {
+        let content_length = resp.content_length().unwrap_or(0);
+        if content_length > limit as u64 && !size_limits.truncate {
+            return Err(size_limits.to_error(url, limit));
+        }
+    }
+
+    // the read timeout of the client doesn't stop servers which send their content bit by bit.
+    // NOTE: the `Content-Length` may be missing or wrong so the limit is checked while reading
+    let mut buffer = [0; 8192];
+    loop {
+        let len = resp.read(&mut buffer)?;
+        if len == 0 {
+            break;
+        }
+        if let Some(limit) = limit.filter(|limit| response.content.len() + len > *limit) {
+            if !size_limits.truncate {
+                return Err(size_limits.to_error(url, limit));
+            }
+            let rest = limit - response.content.len();
+            response.content.extend_from_slice(&buffer[..rest]);
+            response.truncated = true;
+            break;
+        }
+        response.content.extend_from_slice(&buffer[..len]);
+        if timeout.is_some_and(|timeout| started.elapsed() > timeout) {
+            let message = format!("Timed out fetching URL \"{}\"", url);
+            return Err(IoError(io::Error::new(ErrorKind::TimedOut, message)));
+        }
+    }
+    response.duration = started.elapsed();
+    Ok(response)
+}
+
+// like `send` but follows up to `max_redirects` redirects. Unsuccessful responses are errors
+pub fn send_following(
+    client: &Client,
+    url: &Url,
+    content_type_blacklist: Option<Vec<&str>>,
+    max_redirects: usize,
+    size_limits: &SizeLimits,
+) -> shared::Result<Response> {
+    let mut response = send(
+        client,
+        url,
+        HeaderMap::new(),
+        content_type_blacklist.clone(),
+        None,
+        size_limits,
+    )?;
+    let mut redirects = vec![];
+    let mut duration = response.duration;
+    while let Some(location) = response.get_location() {
+        if redirects.len() >= max_redirects {
+            return Err(RequestError(format!(
+                "Too many redirects for URL \"{}\"",
+                url
+            )));
+        }
+        redirects.push(response.url);
+        response = send(
+            client,
+            &location,
+            HeaderMap::new(),
+            content_type_blacklist.clone(),
+            None,
+            size_limits,
+        )?;
+        duration += response.duration;
+    }
+    if !response.is_success() {
+        return Err(response.to_error());
+    }
+    response.redirects = redirects;
+    response.duration = duration;
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::response::{send, Response, SizeLimits, CLIENT};
+    use reqwest::header::{HeaderMap, LOCATION};
+    use reqwest::Url;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    // answers a single request with `content`. Without a `Content-Length` the content ends when
+    // the connection is closed
+    fn serve(content: &'static [u8], content_length: Option<usize>) -> Url {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let _ = stream.read(&mut [0; 1024]).unwrap();
+            let mut head = "HTTP/1.1 200 OK\r\nContent-Type: text/html\r\n".to_string();
+            if let Some(content_length) = content_length {
+                head.push_str(&format!("Content-Length: {}\r\n", content_length));
+            }
+            head.push_str("\r\n");
+            stream.write_all(head.as_bytes()).unwrap_or(());
+            stream.write_all(content).unwrap_or(());
+        });
+        Url::parse(&url).unwrap()
+    }
+
+    #[test]
+    fn response_requested_url() {
+        let url = Url::parse("http://example.com/new").unwrap();
+        let mut response = Response::new(url.clone(), "text/html", vec![]);
+
+        assert_eq!(response.get_requested_url(), &url);
+        response.redirects = vec![
+            Url::parse("http://example.com/old").unwrap(),
+            Url::parse("https://example.com/old").unwrap(),
+        ];
+        assert_eq!(
+            response.get_requested_url().as_str(),
+            "http://example.com/old"
+        );
+    }
+
+    #[test]
+    fn response_location() {
+        let url = Url::parse("http://example.com/a/b").unwrap();
+        let mut response = Response::new(url, "text/html", vec![]);
+
+        response.headers.insert(LOCATION, "../c".parse().unwrap());
+        assert_eq!(response.get_location(), None);
+        response.status = 301;
+        assert_eq!(
+            response.get_location().unwrap().as_str(),
+            "http://example.com/c"
+        );
+        response.headers.clear();
+        assert_eq!(response.get_location(), None);
+    }
+
+    #[test]
+    fn response_to_error() {
+        let url = Url::parse("http://example.com/").unwrap();
+        let mut response = Response::new(url, "text/html", vec![]);
+
+        assert!(response.is_success());
+        response.status = 503;
+        assert!(!response.is_success());
+        assert_eq!(response.to_error().to_string(), "503 Service Unavailable");
+    }
+
+    #[test]
+    fn size_limits() {
+        let limits = SizeLimits {
+            max_size: Some(100),
+            by_content_type: vec![
+                ("html".to_string(), 10),
+                ("application/pdf".to_string(), 1000),
+            ],
+            truncate: false,
+        };
+
+        assert_eq!(limits.get_limit("text/html; charset=utf-8"), Some(10));
+        assert_eq!(limits.get_limit("application/pdf"), Some(1000));
+        assert_eq!(limits.get_limit("text/plain"), Some(100));
+        let unlimited = SizeLimits {
+            max_size: None,
+            ..limits
+        };
+        assert_eq!(unlimited.get_limit("text/plain"), None);
+        assert_eq!(unlimited.get_limit("text/html"), Some(10));
+    }
+
+    #[test]
+    fn send_size_limits() {
+        let mut limits = SizeLimits {
+            max_size: Some(5),
+            by_content_type: vec![],
+            truncate: false,
+        };
+
+        let url = serve(b"Hello", Some(5));
+        let response = send(&CLIENT, &url, HeaderMap::new(), None, None, &limits).unwrap();
+        assert_eq!(response.content, b"Hello");
+        assert!(!response.truncated);
+
+        // the announced size is too large
+        let url = serve(b"Hello World!", Some(12));
+        let err = send(&CLIENT, &url, HeaderMap::new(), None, None, &limits).unwrap_err();
+        assert_eq!(err.get_kind(), "SizeError");
+
+        // the size isn't known until the content was read
+        let url = serve(b"Hello World!", None);
+        let err = send(&CLIENT, &url, HeaderMap::new(), None, None, &limits).unwrap_err();
+        assert_eq!(err.get_kind(), "SizeError");
+
+        limits.truncate = true;
+        let url = serve(b"Hello World!", Some(12));
+        let response = send(&CLIENT, &url, HeaderMap::new(), None, None, &limits).unwrap();
+        assert_eq!(response.content, b"Hello");
+        assert!(response.truncated);
+    }
+}
@@ -0,0 +1,201 @@
+use crate::error::CrawlerError;
+use chrono::{DateTime, Utc};
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use std::collections::hash_map::RandomState;
+use std::error::Error;
+use std::hash::{BuildHasher, Hasher};
+use std::io::{self, ErrorKind};
+use std::time::Duration;
+
+// statuses which are likely to go away if the request is sent again later on
+static TRANSIENT_STATUSES: [u16; 7] = [408, 425, 429, 500, 502, 503, 504];
+// failures of a connection which was established before. A refused connection or a host which
+// can't be resolved usually means a dead link
+static TRANSIENT_IO_ERRORS: [ErrorKind; 6] = [
+    ErrorKind::TimedOut,
+    ErrorKind::ConnectionReset,
+    ErrorKind::ConnectionAborted,
+    ErrorKind::BrokenPipe,
+    ErrorKind::UnexpectedEof,
+    ErrorKind::Interrupted,
+];
+
+// decides how often and after which delay a failed fetch is tried again. The delay doubles with
+// every retry and a random part of it is used (full jitter) so that retries don't come in waves
+#[derive(PartialEq, Debug, Clone)]
+pub struct RetryPolicy {
+    pub max_retries: usize,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    // the longest `Retry-After` which is waited for. Servers which ask to wait longer are given up
+    // on
+    pub max_retry_after: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_retries: 3,
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(60),
+            max_retry_after: Duration::from_secs(600),
+        }
+    }
+}
+
+impl RetryPolicy {
+    // the delay before the next retry of a job which was already retried `retries` times. A
+    // `Retry-After` from the server is honored. `None` if the job shouldn't be retried anymore
+    pub fn get_delay(&self, retries: usize, retry_after: Option<Duration>) -> Option<Duration> {
+        if retries >= self.max_retries {
+            return None;
+        }
+        if let Some(retry_after) = retry_after {
+            return Some(retry_after).filter(|retry_after| *retry_after <= self.max_retry_after);
+        }
+        let backoff = self
+            .base_delay
+            .checked_mul(1 << retries.min(31))
+            .unwrap_or(self.max_delay)
+            .min(self.max_delay);
+        let jitter = random() as f64 / u64::MAX as f64;
+        Some(backoff.mul_f64(jitter))
+    }
+}
+
+// timeouts, dropped connections and statuses like `503` are transient. Everything else, e.g. a
+// `404`, a host which doesn't exist or a blacklisted content type, won't change by trying again
+pub fn is_transient(err: &CrawlerError) -> bool {
+    match err {
+        CrawlerError::ReqwestError(err) => {
+            err.is_timeout() || get_io_error_kind(err).is_some_and(is_transient_kind)
+        }
+        CrawlerError::IoError(err) => is_transient_kind(err.kind()),
+        // e.g. "503 Service Unavailable"
+        CrawlerError::RequestError(status) => status
+            .split(' ')
+            .next()
+            .and_then(|code| code.parse::<u16>().ok())
+            .is_some_and(|code| TRANSIENT_STATUSES.contains(&code)),
+        _ => false,
+    }
+}
+
+fn is_transient_kind(kind: ErrorKind) -> bool {
+    TRANSIENT_IO_ERRORS.contains(&kind)
+}
+
+// the kind of the IO error a request failed with, if any
+fn get_io_error_kind(err: &reqwest::Error) -> Option<ErrorKind> {
+    let mut source: Option<&(dyn Error + 'static)> = err.get_ref().map(|err| err as _);
+    while let Some(err) = source {
+        if let Some(err) = err.downcast_ref::<io::Error>() {
+            return Some(err.kind());
+        }
+        source = err.source();
+    }
+    None
+}
+
+// reads `Retry-After` which is either a number of seconds or a date
+pub fn get_retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = DateTime::parse_from_rfc2822(value).ok()?;
+    let delay = date.with_timezone(&Utc) - Utc::now();
+    Some(delay.to_std().unwrap_or(Duration::from_secs(0)))
+}
+
+// every `RandomState` is seeded differently which is good enough for jitter
+fn random() -> u64 {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u8(0);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::CrawlerError::{IoError, ParsingError, RequestError, ReqwestError};
+    use crate::response::CLIENT;
+    use crate::retry::{get_retry_after, is_transient, RetryPolicy};
+    use chrono::{Duration as ChronoDuration, Utc};
+    use reqwest::header::{HeaderMap, RETRY_AFTER};
+    use std::io::{Error, ErrorKind};
+    use std::net::TcpListener;
+    use std::time::Duration;
+
+    #[test]
+    fn retry_policy_delay() {
+        let policy = RetryPolicy {
+            max_retries: 3,
+            base_delay: Duration::from_secs(10),
+            max_delay: Duration::from_secs(25),
+            max_retry_after: Duration::from_secs(120),
+        };
+
+        assert!(policy.get_delay(0, None).unwrap() <= Duration::from_secs(10));
+        assert!(policy.get_delay(1, None).unwrap() <= Duration::from_secs(20));
+        assert!(policy.get_delay(2, None).unwrap() <= Duration::from_secs(25));
+        assert_eq!(policy.get_delay(3, None), None);
+
+        let retry_after = Some(Duration::from_secs(20));
+        assert_eq!(policy.get_delay(0, retry_after), retry_after);
+        // a `Retry-After` isn't capped by the maximum delay of the backoff
+        let retry_after = Some(Duration::from_secs(120));
+        assert_eq!(policy.get_delay(0, retry_after), retry_after);
+        assert_eq!(policy.get_delay(0, Some(Duration::from_secs(121))), None);
+    }
+
+    #[test]
+    fn retry_is_transient() {
+        assert!(is_transient(&RequestError(
+            "503 Service Unavailable".to_string()
+        )));
+        assert!(is_transient(&RequestError(
+            "429 Too Many Requests".to_string()
+        )));
+        assert!(!is_transient(&RequestError("404 Not Found".to_string())));
+        assert!(!is_transient(&RequestError(
+            "Too many redirects".to_string()
+        )));
+        assert!(is_transient(&IoError(Error::from(ErrorKind::TimedOut))));
+        assert!(!is_transient(&IoError(Error::from(ErrorKind::NotFound))));
+        assert!(!is_transient(&IoError(Error::from(
+            ErrorKind::ConnectionRefused
+        ))));
+
+        // nobody listens on the port once the listener is dropped
+        let addr = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let err = CLIENT.get(&format!("http://{}/", addr)).send().unwrap_err();
+        assert!(!is_transient(&ReqwestError(err)));
+        assert!(!is_transient(&ParsingError("503".to_string())));
+    }
+
+    #[test]
+    fn retry_after() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(get_retry_after(&headers), None);
+
+        headers.insert(RETRY_AFTER, "120".parse().unwrap());
+        assert_eq!(get_retry_after(&headers), Some(Duration::from_secs(120)));
+
+        let date = (Utc::now() + ChronoDuration::seconds(60)).to_rfc2822();
+        headers.insert(RETRY_AFTER, date.parse().unwrap());
+        let delay = get_retry_after(&headers).unwrap();
+        assert!(delay > Duration::from_secs(55) && delay <= Duration::from_secs(60));
+
+        headers.insert(
+            RETRY_AFTER,
+            "Wed, 21 Oct 2015 07:28:00 GMT".parse().unwrap(),
+        );
+        assert_eq!(get_retry_after(&headers), Some(Duration::from_secs(0)));
+
+        headers.insert(RETRY_AFTER, "soon".parse().unwrap());
+        assert_eq!(get_retry_after(&headers), None);
+    }
+}
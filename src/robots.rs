@@ -0,0 +1,485 @@
+use crate::response::MAX_REDIRECTS;
+use crate::retry;
+use crate::traits::{Fetch, Observe};
+use reqwest::Url;
+use std::collections::HashMap;
+use std::mem;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+// how long everything is disallowed on a host whose `robots.txt` failed for a transient reason
+// before it's fetched again
+static UNREACHABLE_TTL: Duration = Duration::from_secs(60);
+
+#[derive(PartialEq, Debug, Clone)]
+struct Rule {
+    allow: bool,
+    pattern: String,
+}
+
+#[derive(Default)]
+struct Group {
+    user_agents: Vec<String>,
+    rules: Vec<Rule>,
+    crawl_delay: Option<Duration>,
+}
+
+#[derive(PartialEq, Debug, Default, Clone)]
+pub struct Robots {
+    rules: Vec<Rule>,
+    crawl_delay: Option<Duration>,
+    // the rules only stand in for a `robots.txt` which couldn't be fetched until then
+    expires: Option<Instant>,
+}
+
+impl Robots {
+    // the user agents of the groups are compared to the product token of ours, e.g. `MyBot` for
+    // `MyBot/1.0`
+    pub fn parse(content: &str, user_agent: &str) -> Self {
+        let groups = Self::parse_groups(content);
+        let product_token = get_product_token(user_agent);
+
+        // rules for our user agent take precedence over the ones for all user agents
+        let matches_user_agent = |group: &&Group| {
+            group.user_agents.iter().any(|agent| {
+                agent != "*" && get_product_token(agent).eq_ignore_ascii_case(product_token)
+            })
+        };
+        let matches_any = |group: &&Group| group.user_agents.iter().any(|agent| agent == "*");
+        let mut selected: Vec<&Group> = groups.iter().filter(matches_user_agent).collect();
+        if selected.is_empty() {
+            selected = groups.iter().filter(matches_any).collect();
+        }
+
+        let mut robots = Robots::default();
+        for group in selected {
+            robots.rules.extend(group.rules.iter().cloned());
+            if robots.crawl_delay.is_none() {
+                robots.crawl_delay = group.crawl_delay;
+            }
+        }
+        robots
+    }
+
+    fn parse_groups(content: &str) -> Vec<Group> {
+        let mut groups: Vec<Group> = vec![];
+        let mut current = Group::default();
+        for line in content.lines() {
+            // strip comments
+            let line = match line.find('#') {
+                Some(idx) => &line[..idx],
+                None => line,
+            };
+            let mut parts = line.splitn(2, ':');
+            let key = parts.next().unwrap_or("").trim().to_lowercase();
+            let value = match parts.next() {
+                Some(value) => value.trim(),
+                None => continue,
+            };
+            match key.as_str() {
+                "user-agent" if !value.is_empty() => {
+                    // a user agent which follows rules starts a new group
+                    if !current.rules.is_empty() || current.crawl_delay.is_some() {
+                        groups.push(current);
+                        current = Group::default();
+                    }
+                    current.user_agents.push(value.to_lowercase());
+                }
+                // an empty `Disallow` allows everything so it's skipped
+                "allow" | "disallow" if !current.user_agents.is_empty() && !value.is_empty() => {
+                    current.rules.push(Rule {
+                        allow: key == "allow",
+                        pattern: value.to_string(),
+                    });
+                }
+                "crawl-delay" if !current.user_agents.is_empty() => {
+                    if let Ok(seconds) = value.parse::<f64>() {
+                        if seconds >= 0.0 && seconds.is_finite() {
+                            current.crawl_delay = Some(Duration::from_secs_f64(seconds));
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        if !current.user_agents.is_empty() {
+            groups.push(current);
+        }
+        groups
+    }
+
+    pub fn allows(&self, url: &Url) -> bool {
+        let path = match url.query() {
+            Some(query) => format!("{}?{}", url.path(), query),
+            None => url.path().to_string(),
+        };
+        // the longest matching rule wins, `Allow` wins if rules are equally long
+        let mut decision: Option<(usize, bool)> = None;
+        for rule in self.rules.iter() {
+            if !matches(&rule.pattern, &path) {
+                continue;
+            }
+            let len = rule.pattern.len();
+            decision = match decision {
+                Some((best, allow)) if best > len || (best == len && allow) => Some((best, allow)),
+                _ => Some((len, rule.allow)),
+            };
+        }
+        decision.is_none_or(|(_, allow)| allow)
+    }
+
+    pub fn get_crawl_delay(&self) -> Option<Duration> {
+        self.crawl_delay
+    }
+
+    // a server error means that the site might not want to be crawled right now (see RFC 9309)
+    fn unreachable(now: Instant) -> Self {
+        Robots {
+            rules: vec![Rule {
+                allow: false,
+                pattern: "/".to_string(),
+            }],
+            crawl_delay: None,
+            expires: Some(now + UNREACHABLE_TTL),
+        }
+    }
+
+    fn is_expired(&self, now: Instant) -> bool {
+        self.expires.is_some_and(|expires| expires <= now)
+    }
+}
+
+// the name which `robots.txt` addresses a crawler by, e.g. `MyBot` for
+// `MyBot/1.0 (+https://example.com/bot)`
+pub fn get_product_token(user_agent: &str) -> &str {
+    user_agent
+        .trim()
+        .split(|c: char| c == '/' || c.is_whitespace())
+        .next()
+        .unwrap_or("")
+}
+
+// supports `*` as a wildcard for any sequence of characters and `$` to anchor the end of the path
+fn matches(pattern: &str, path: &str) -> bool {
+    let (pattern, anchored) = match pattern.strip_suffix('$') {
+        Some(pattern) => (pattern, true),
+        None => (pattern, false),
+    };
+    let parts: Vec<&str> = pattern.split('*').collect();
+    if parts.len() == 1 {
+        return if anchored {
+            path == pattern
+        } else {
+            path.starts_with(pattern)
+        };
+    }
+    if !path.starts_with(parts[0]) {
+        return false;
+    }
+    let mut rest = &path[parts[0].len()..];
+    let last = parts.len() - 1;
+    for part in parts[1..last].iter() {
+        match rest.find(part) {
+            Some(idx) => rest = &rest[idx + part.len()..],
+            None => return false,
+        }
+    }
+    if anchored {
+        rest.ends_with(parts[last])
+    } else {
+        rest.contains(parts[last])
+    }
+}
+
+pub struct RobotsCache {
+    user_agent: String,
+    // every origin gets its own cell so that `robots.txt` is only fetched once per origin
+    robots: Mutex<HashMap<String, Arc<OnceLock<Arc<Robots>>>>>,
+    // the `robots.txt` URLs which were fetched since they were last taken
+    fetched: Mutex<Vec<Url>>,
+    observer: Arc<dyn Observe>,
+}
+
+impl RobotsCache {
+    // the observer is told about the requests for `robots.txt` like about the ones for pages
+    pub fn new(user_agent: &str, observer: Arc<dyn Observe>) -> Self {
+        RobotsCache {
+            user_agent: user_agent.to_string(),
+            robots: Mutex::new(HashMap::new()),
+            fetched: Mutex::new(vec![]),
+            observer,
+        }
+    }
+
+    pub fn get<T>(&self, fetcher: &T, url: &Url) -> Arc<Robots>
+    where
+        T: Fetch,
+    {
+        let origin = url.origin().ascii_serialization();
+        let cell = {
+            let mut robots = self.robots.lock().unwrap();
+            let cell = robots.entry(origin).or_default();
+            if cell
+                .get()
+                .is_some_and(|robots| robots.is_expired(Instant::now()))
+            {
+                *cell = Arc::default();
+            }
+            cell.clone()
+        };
+        cell.get_or_init(|| Arc::new(self.fetch(fetcher, url)))
+            .clone()
+    }
+
+    // a missing `robots.txt` allows everything. One which fails for a transient reason, e.g. with a
+    // `503`, disallows everything for a while
+    fn fetch<T>(&self, fetcher: &T, url: &Url) -> Robots
+    where
+        T: Fetch,
+    {
+        let robots_url = match url.join("/robots.txt") {
+            Ok(robots_url) => robots_url,
+            Err(_) => return Robots::default(),
+        };
+        log!(format!("GET {}", &robots_url));
+        self.fetched.lock().unwrap().push(robots_url.clone());
+        self.observer.on_fetch_start(&robots_url);
+        let mut response = fetcher.fetch_response(&robots_url);
+        // e.g. from `http` to `https`
+        for _ in 0..MAX_REDIRECTS {
+            match response
+                .as_ref()
+                .ok()
+                .and_then(|response| response.get_location())
+            {
+                Some(location) => response = fetcher.fetch_response(&location),
+                None => break,
+            }
+        }
+        let err = match response {
+            Ok(response) => {
+                let (status, len) = (Some(response.status), response.content.len());
+                self.observer
+                    .on_fetch_done(&robots_url, status, len, response.duration);
+                if response.is_success() {
+                    let content = String::from_utf8_lossy(&response.content);
+                    return Robots::parse(&content, &self.user_agent);
+                }
+                response.to_error()
+            }
+            Err(err) => err,
+        };
+        if retry::is_transient(&err) {
+            Robots::unreachable(Instant::now())
+        } else {
+            Robots::default()
+        }
+    }
+
+    // the `robots.txt` URLs which were fetched since the last call so that the requests count
+    // towards the delay of their hosts
+    pub fn take_fetched(&self) -> Vec<Url> {
+        mem::take(&mut *self.fetched.lock().unwrap())
+    }
+
+    // only returns the rules for the URL if they were already fetched
+    pub fn get_cached(&self, url: &Url) -> Option<Arc<Robots>> {
+        let origin = url.origin().ascii_serialization();
+        let robots = self.robots.lock().unwrap();
+        robots.get(&origin).and_then(|cell| cell.get().cloned())
+    }
+
+    pub fn allows<T>(&self, fetcher: &T, url: &Url) -> bool
+    where
+        T: Fetch,
+    {
+        self.get(fetcher, url).allows(url)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::CrawlerError::RequestError;
+    use crate::robots::{get_product_token, matches, Robots, RobotsCache};
+    use crate::shared;
+    use crate::traits::{Fetch, Observe};
+    use reqwest::Url;
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, Instant};
+
+    struct MockFetcher {
+        requests: Mutex<Vec<String>>,
+    }
+    impl Fetch for MockFetcher {
+        fn fetch(&self, url: &str) -> shared::Result<(String, Vec<u8>)> {
+            self.requests.lock().unwrap().push(url.to_string());
+            if url.starts_with("http://example.com") {
+                let content = b"User-agent: *\nDisallow: /private\n".to_vec();
+                return Ok(("text/plain".to_string(), content));
+            }
+            if url.starts_with("http://unavailable.com") {
+                return Err(RequestError("503 Service Unavailable".to_string()));
+            }
+            Err(RequestError("404 Not Found".to_string()))
+        }
+    }
+
+    #[derive(Default)]
+    struct MockObserver {
+        fetched: Mutex<Vec<String>>,
+    }
+    impl Observe for MockObserver {
+        fn on_fetch_start(&self, url: &Url) {
+            self.fetched.lock().unwrap().push(url.to_string());
+        }
+    }
+
+    fn new_cache() -> (RobotsCache, Arc<MockObserver>) {
+        let observer = Arc::new(MockObserver::default());
+        (RobotsCache::new("crawler", observer.clone()), observer)
+    }
+
+    fn get_url(url: &str) -> Url {
+        Url::parse(url).unwrap()
+    }
+
+    static ROBOTS: &str = "# robots.txt for example.com
+User-agent: *
+Disallow: /private
+Disallow: /*.pdf$
+Allow: /private/public
+Crawl-delay: 5
+
+User-agent: crawler
+User-agent: other
+Disallow: /crawler-only # comment
+Crawl-delay: 0.5
+";
+
+    #[test]
+    fn robots_any_user_agent() {
+        let robots = Robots::parse(ROBOTS, "SomeBot/1.0");
+
+        assert!(robots.allows(&get_url("http://example.com/")));
+        assert!(robots.allows(&get_url("http://example.com/crawler-only")));
+        assert!(!robots.allows(&get_url("http://example.com/private")));
+        assert!(!robots.allows(&get_url("http://example.com/private/foo")));
+        assert!(robots.allows(&get_url("http://example.com/private/public/foo")));
+        assert!(!robots.allows(&get_url("http://example.com/docs/foo.pdf")));
+        assert!(robots.allows(&get_url("http://example.com/docs/foo.pdf?bar")));
+        assert_eq!(robots.get_crawl_delay(), Some(Duration::from_secs(5)));
+    }
+
+    #[test]
+    fn robots_matching_user_agent() {
+        let robots = Robots::parse(ROBOTS, "Crawler/0.1");
+
+        assert!(!robots.allows(&get_url("http://example.com/crawler-only/foo")));
+        assert!(robots.allows(&get_url("http://example.com/private")));
+        assert_eq!(robots.get_crawl_delay(), Some(Duration::from_millis(500)));
+    }
+
+    #[test]
+    fn robots_user_agent_product_token() {
+        // the product token has to match exactly, regardless of case
+        let robots = Robots::parse(ROBOTS, "SuperCrawler/1.0");
+        assert!(!robots.allows(&get_url("http://example.com/private")));
+
+        let robots = Robots::parse("User-agent: MyBot/2.0\nDisallow: /\n", "mybot");
+        assert!(!robots.allows(&get_url("http://example.com/foo")));
+    }
+
+    #[test]
+    fn robots_product_token() {
+        assert_eq!(
+            get_product_token("MyBot/1.0 (+https://example.com/bot)"),
+            "MyBot"
+        );
+        assert_eq!(get_product_token(" MyBot"), "MyBot");
+        assert_eq!(get_product_token("My Bot/1.0"), "My");
+        assert_eq!(get_product_token(""), "");
+    }
+
+    #[test]
+    fn robots_empty() {
+        let robots = Robots::parse("", "crawler");
+
+        assert!(robots.allows(&get_url("http://example.com/foo")));
+        assert_eq!(robots.get_crawl_delay(), None);
+    }
+
+    #[test]
+    fn robots_empty_disallow() {
+        let robots = Robots::parse("User-agent: *\nDisallow:\n", "crawler");
+
+        assert!(robots.allows(&get_url("http://example.com/foo")));
+    }
+
+    #[test]
+    fn robots_matches() {
+        assert!(matches("/foo", "/foo/bar"));
+        assert!(!matches("/foo$", "/foo/bar"));
+        assert!(matches("/foo$", "/foo"));
+        assert!(matches("/*/bar", "/foo/bar"));
+        assert!(matches("/*/bar", "/foo/bar/baz"));
+        assert!(!matches("/*/bar$", "/foo/bar/baz"));
+        assert!(matches("*.php", "/index.php?foo"));
+        assert!(matches("/a*b*c$", "/a-b-b-c"));
+        assert!(!matches("/a*b*c$", "/a-c-b"));
+    }
+
+    #[test]
+    fn robots_cache() {
+        let fetcher = MockFetcher {
+            requests: Mutex::new(vec![]),
+        };
+        let (cache, observer) = new_cache();
+
+        assert_eq!(cache.get_cached(&get_url("http://example.com/foo")), None);
+        assert!(cache.allows(&fetcher, &get_url("http://example.com/foo")));
+        assert!(cache
+            .get_cached(&get_url("http://example.com/bar"))
+            .is_some());
+        assert!(!cache.allows(&fetcher, &get_url("http://example.com/private")));
+        assert!(cache.allows(&fetcher, &get_url("http://jdoe.com/private")));
+        assert!(cache.allows(&fetcher, &get_url("http://jdoe.com/foo")));
+
+        // `robots.txt` is only fetched once per origin
+        assert_eq!(
+            *fetcher.requests.lock().unwrap(),
+            vec![
+                "http://example.com/robots.txt".to_string(),
+                "http://jdoe.com/robots.txt".to_string()
+            ]
+        );
+        assert_eq!(
+            *observer.fetched.lock().unwrap(),
+            *fetcher.requests.lock().unwrap()
+        );
+        assert_eq!(
+            cache.take_fetched(),
+            vec![
+                get_url("http://example.com/robots.txt"),
+                get_url("http://jdoe.com/robots.txt")
+            ]
+        );
+        assert!(cache.take_fetched().is_empty());
+    }
+
+    #[test]
+    fn robots_cache_unreachable() {
+        let fetcher = MockFetcher {
+            requests: Mutex::new(vec![]),
+        };
+        let (cache, _) = new_cache();
+        let url = get_url("http://unavailable.com/foo");
+
+        // a server error disallows everything until the rules expire
+        assert!(!cache.allows(&fetcher, &url));
+        assert!(!cache.allows(&fetcher, &url));
+        assert_eq!(fetcher.requests.lock().unwrap().len(), 1);
+        let robots = cache.get_cached(&url).unwrap();
+        assert!(!robots.is_expired(Instant::now()));
+        assert!(robots.is_expired(Instant::now() + Duration::from_secs(60)));
+    }
+}
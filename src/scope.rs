@@ -0,0 +1,186 @@
+use reqwest::Url;
+use std::str::FromStr;
+
+// NOTE: this is by no means a complete list of public suffixes. It only covers the most common
+// ones with two labels so that e.g. `example.co.uk` and not `co.uk` is used as the domain
+static TWO_LABEL_SUFFIXES: [&str; 12] = [
+    "co.uk", "org.uk", "ac.uk", "gov.uk", "com.au", "net.au", "org.au", "co.nz", "co.jp", "com.br",
+    "com.cn", "co.in",
+];
+
+#[derive(PartialEq, Debug, Clone, Default)]
+pub enum Scope {
+    // follow every link
+    #[default]
+    Any,
+    // only follow links to the host of the starting URL
+    SameHost,
+    // only follow links to the domain of the starting URL including its subdomains
+    SameDomain,
+    // only follow links which are below the path of the starting URL
+    Prefix,
+    // only follow links to the given hosts
+    Hosts(Vec<String>),
+}
+
+impl Scope {
+    pub fn allows(&self, seed: &Url, url: &Url) -> bool {
+        match self {
+            Scope::Any => true,
+            Scope::SameHost => url.host_str().is_some() && url.host_str() == seed.host_str(),
+            Scope::SameDomain => match (seed.host_str(), url.host_str()) {
+                (Some(seed_host), Some(host)) => {
+                    let domain = registrable_domain(seed_host);
+                    host == domain || host.ends_with(format!(".{}", domain).as_str())
+                }
+                _ => false,
+            },
+            Scope::Prefix => url.as_str().starts_with(prefix(seed).as_str()),
+            Scope::Hosts(hosts) => url
+                .host_str()
+                .is_some_and(|host| hosts.iter().any(|allowed| allowed == host)),
+        }
+    }
+}
+
+impl FromStr for Scope {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "any" => Ok(Scope::Any),
+            "host" => Ok(Scope::SameHost),
+            "domain" => Ok(Scope::SameDomain),
+            "prefix" => Ok(Scope::Prefix),
+            _ if value.starts_with("hosts:") => {
+                let hosts: Vec<String> = value["hosts:".len()..]
+                    .split(',')
+                    .filter(|host| !host.is_empty())
+                    .map(|host| host.to_lowercase())
+                    .collect();
+                if hosts.is_empty() {
+                    return Err(format!("No hosts given in scope \"{}\"", value));
+                }
+                Ok(Scope::Hosts(hosts))
+            }
+            _ => Err(format!("Unknown scope \"{}\"", value)),
+        }
+    }
+}
+
+// everything up to (and including) the last `/` of the URLs path
+fn prefix(url: &Url) -> String {
+    let mut prefix = url.clone();
+    prefix.set_query(None);
+    prefix.set_fragment(None);
+    let prefix = prefix.as_str();
+    match prefix.rfind('/') {
+        Some(idx) => prefix[..=idx].to_string(),
+        None => prefix.to_string(),
+    }
+}
+
+pub fn registrable_domain(host: &str) -> &str {
+    // IP addresses don't have any subdomains
+    if host.parse::<std::net::IpAddr>().is_ok() || host.starts_with('[') {
+        return host;
+    }
+    let labels: Vec<&str> = host.split('.').collect();
+    let num_labels = if labels.len() > 2
+        && TWO_LABEL_SUFFIXES.contains(&labels[labels.len() - 2..].join(".").as_str())
+    {
+        3
+    } else {
+        2
+    };
+    if labels.len() <= num_labels {
+        return host;
+    }
+    let skip: usize = labels[..labels.len() - num_labels]
+        .iter()
+        .map(|label| label.len() + 1)
+        .sum();
+    &host[skip..]
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::scope::{registrable_domain, Scope};
+    use reqwest::Url;
+
+    fn get_url(url: &str) -> Url {
+        Url::parse(url).unwrap()
+    }
+
+    #[test]
+    fn scope_any() {
+        let seed = get_url("http://example.com");
+
+        assert!(Scope::Any.allows(&seed, &get_url("http://example.com/foo")));
+        assert!(Scope::Any.allows(&seed, &get_url("https://jdoe.com")));
+    }
+
+    #[test]
+    fn scope_same_host() {
+        let seed = get_url("http://example.com/foo");
+
+        assert!(Scope::SameHost.allows(&seed, &get_url("https://example.com/bar")));
+        assert!(!Scope::SameHost.allows(&seed, &get_url("http://docs.example.com")));
+        assert!(!Scope::SameHost.allows(&seed, &get_url("http://jdoe.com")));
+    }
+
+    #[test]
+    fn scope_same_domain() {
+        let seed = get_url("http://www.example.com");
+
+        assert!(Scope::SameDomain.allows(&seed, &get_url("http://example.com")));
+        assert!(Scope::SameDomain.allows(&seed, &get_url("http://docs.example.com/foo")));
+        assert!(!Scope::SameDomain.allows(&seed, &get_url("http://myexample.com")));
+        assert!(!Scope::SameDomain.allows(&seed, &get_url("http://example.org")));
+    }
+
+    #[test]
+    fn scope_prefix() {
+        let seed = get_url("http://example.com/docs/intro?foo=bar");
+
+        assert!(Scope::Prefix.allows(&seed, &get_url("http://example.com/docs/")));
+        assert!(Scope::Prefix.allows(&seed, &get_url("http://example.com/docs/api/foo")));
+        assert!(!Scope::Prefix.allows(&seed, &get_url("http://example.com/blog")));
+        assert!(!Scope::Prefix.allows(&seed, &get_url("https://example.com/docs/")));
+    }
+
+    #[test]
+    fn scope_hosts() {
+        let seed = get_url("http://example.com");
+        let scope = Scope::Hosts(vec!["example.com".to_string(), "jdoe.com".to_string()]);
+
+        assert!(scope.allows(&seed, &get_url("http://jdoe.com/foo")));
+        assert!(!scope.allows(&seed, &get_url("http://www.jdoe.com")));
+    }
+
+    #[test]
+    fn scope_from_str() {
+        assert_eq!("any".parse::<Scope>(), Ok(Scope::Any));
+        assert_eq!("host".parse::<Scope>(), Ok(Scope::SameHost));
+        assert_eq!("domain".parse::<Scope>(), Ok(Scope::SameDomain));
+        assert_eq!("prefix".parse::<Scope>(), Ok(Scope::Prefix));
+        assert_eq!(
+            "hosts:example.com,JDOE.com".parse::<Scope>(),
+            Ok(Scope::Hosts(vec![
+                "example.com".to_string(),
+                "jdoe.com".to_string()
+            ]))
+        );
+        assert!("hosts:".parse::<Scope>().is_err());
+        assert!("foo".parse::<Scope>().is_err());
+    }
+
+    #[test]
+    fn scope_registrable_domain() {
+        assert_eq!(registrable_domain("example.com"), "example.com");
+        assert_eq!(registrable_domain("docs.example.com"), "example.com");
+        assert_eq!(registrable_domain("a.b.example.co.uk"), "example.co.uk");
+        assert_eq!(registrable_domain("127.0.0.1"), "127.0.0.1");
+        assert_eq!(registrable_domain("localhost"), "localhost");
+    }
+}
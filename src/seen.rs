@@ -0,0 +1,290 @@
+use std::collections::HashSet;
+use std::io::{self, Write};
+
+// the false positive rate of the first layer. Every following layer halves it so that the overall
+// rate stays below twice this value no matter how many layers are added
+static FALSE_POSITIVE_RATE: f64 = 0.001;
+
+// the 64 bit FNV-1a hash of a key, e.g. a canonical URL. Unlike the hashers of the standard library
+// it's the same on every platform and Rust release, so the fingerprints of a checkpoint stay valid
+pub fn fingerprint(key: &str) -> u64 {
+    key.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+// derives a second, independent hash from a fingerprint (see SplitMix64)
+fn mix(fingerprint: u64) -> u64 {
+    let mut z = fingerprint.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+struct Layer {
+    bits: Vec<u64>,
+    num_bits: u64,
+    num_hashes: u32,
+    capacity: usize,
+    len: usize,
+}
+
+impl Layer {
+    fn new(capacity: usize, false_positive_rate: f64) -> Self {
+        let ln2 = std::f64::consts::LN_2;
+        let num_bits = (-(capacity as f64) * false_positive_rate.ln() / (ln2 * ln2)).ceil() as u64;
+        let num_bits = num_bits.max(64);
+        let num_hashes = ((num_bits as f64 / capacity as f64) * ln2).round().max(1.0) as u32;
+        Layer {
+            bits: vec![0; num_bits.div_ceil(64) as usize],
+            num_bits,
+            num_hashes,
+            capacity,
+            len: 0,
+        }
+    }
+
+    // double hashing to get the positions of all bits for a fingerprint
+    fn positions(&self, fingerprint: u64) -> impl Iterator<Item = u64> + '_ {
+        let h1 = fingerprint;
+        let h2 = mix(fingerprint) | 1;
+        (0..u64::from(self.num_hashes))
+            .map(move |i| h1.wrapping_add(i.wrapping_mul(h2)) % self.num_bits)
+    }
+
+    fn insert(&mut self, fingerprint: u64) {
+        let positions: Vec<u64> = self.positions(fingerprint).collect();
+        for pos in positions {
+            self.bits[(pos / 64) as usize] |= 1 << (pos % 64);
+        }
+        self.len += 1;
+    }
+
+    fn contains(&self, fingerprint: u64) -> bool {
+        self.positions(fingerprint)
+            .all(|pos| self.bits[(pos / 64) as usize] & (1 << (pos % 64)) != 0)
+    }
+
+    fn is_full(&self) -> bool {
+        self.len >= self.capacity
+    }
+
+    fn to_line(&self) -> String {
+        let bits: String = self
+            .bits
+            .iter()
+            .map(|word| format!("{:016x}", word))
+            .collect();
+        format!(
+            "layer {} {} {} {} {}",
+            self.capacity, self.num_bits, self.num_hashes, self.len, bits
+        )
+    }
+
+    fn from_line(line: &str) -> Option<Self> {
+        let mut parts = line.strip_prefix("layer ")?.split(' ');
+        let capacity = parts.next()?.parse::<usize>().ok()?;
+        let num_bits = parts.next()?.parse::<u64>().ok()?;
+        let num_hashes = parts.next()?.parse::<u32>().ok()?;
+        let len = parts.next()?.parse::<usize>().ok()?;
+        let hex = parts.next()?;
+        if !hex.is_ascii() || hex.len() as u64 != num_bits.div_ceil(64) * 16 {
+            return None;
+        }
+        let bits = (0..hex.len())
+            .step_by(16)
+            .map(|pos| u64::from_str_radix(&hex[pos..pos + 16], 16).ok())
+            .collect::<Option<Vec<u64>>>()?;
+        Some(Layer {
+            bits,
+            num_bits,
+            num_hashes,
+            capacity,
+            len,
+        })
+    }
+}
+
+// a Bloom filter which grows by adding layers of twice the capacity once the last one is full
+pub struct BloomFilter {
+    layers: Vec<Layer>,
+    capacity: usize,
+}
+
+impl BloomFilter {
+    pub fn new(capacity: usize) -> Self {
+        BloomFilter {
+            layers: vec![],
+            capacity: capacity.max(1),
+        }
+    }
+
+    pub fn insert(&mut self, fingerprint: u64) {
+        if self.layers.last().is_none_or(|layer| layer.is_full()) {
+            let num_layers = self.layers.len() as i32;
+            let capacity = self.capacity << num_layers.min(32);
+            let false_positive_rate = FALSE_POSITIVE_RATE * 0.5_f64.powi(num_layers);
+            self.layers.push(Layer::new(capacity, false_positive_rate));
+        }
+        self.layers.last_mut().unwrap().insert(fingerprint);
+    }
+
+    pub fn contains(&self, fingerprint: u64) -> bool {
+        self.layers.iter().any(|layer| layer.contains(fingerprint))
+    }
+
+    pub fn len(&self) -> usize {
+        self.layers.iter().map(|layer| layer.len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+// remembers fingerprints exactly until the hot set is full. They're then moved into a Bloom
+// filter which trades a small false positive rate for a fraction of the memory
+pub struct SeenFilter {
+    hot: HashSet<u64>,
+    hot_capacity: usize,
+    cold: BloomFilter,
+}
+
+impl SeenFilter {
+    pub fn new(hot_capacity: usize) -> Self {
+        SeenFilter {
+            hot: HashSet::new(),
+            hot_capacity: hot_capacity.max(1),
+            cold: BloomFilter::new(hot_capacity),
+        }
+    }
+
+    // returns `false` if the fingerprint was already seen
+    pub fn insert(&mut self, fingerprint: u64) -> bool {
+        if self.contains(fingerprint) {
+            return false;
+        }
+        if self.hot.len() >= self.hot_capacity {
+            for fingerprint in self.hot.drain() {
+                self.cold.insert(fingerprint);
+            }
+        }
+        self.hot.insert(fingerprint);
+        true
+    }
+
+    pub fn contains(&self, fingerprint: u64) -> bool {
+        self.hot.contains(&fingerprint) || self.cold.contains(fingerprint)
+    }
+
+    pub fn len(&self) -> usize {
+        self.hot.len() + self.cold.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // writes a line per fingerprint of the hot set and a line per layer of the Bloom filter
+    pub fn write_to<W>(&self, writer: &mut W) -> io::Result<()>
+    where
+        W: Write,
+    {
+        for fingerprint in self.hot.iter() {
+            writeln!(writer, "hot {:016x}", fingerprint)?;
+        }
+        for layer in self.cold.layers.iter() {
+            writeln!(writer, "{}", layer.to_line())?;
+        }
+        Ok(())
+    }
+
+    // restores a line which was written by `write_to`
+    pub fn read_line(&mut self, line: &str) -> Option<()> {
+        if let Some(fingerprint) = line.strip_prefix("hot ") {
+            let fingerprint = u64::from_str_radix(fingerprint, 16).ok()?;
+            self.hot.insert(fingerprint);
+        } else {
+            self.cold.layers.push(Layer::from_line(line)?);
+        }
+        Some(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::seen::{fingerprint, BloomFilter, SeenFilter};
+    use std::io::BufRead;
+
+    #[test]
+    fn fingerprint_is_stable() {
+        assert_eq!(fingerprint(""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fingerprint("a"), 0xaf63_dc4c_8601_ec8c);
+        assert_ne!(
+            fingerprint("http://example.com/1"),
+            fingerprint("http://example.com/2")
+        );
+    }
+
+    #[test]
+    fn bloom_filter() {
+        let mut filter = BloomFilter::new(100);
+
+        assert!(filter.is_empty());
+        for i in 0..1_000 {
+            filter.insert(fingerprint(&i.to_string()));
+        }
+
+        // the filter grew beyond its initial capacity without forgetting anything
+        assert_eq!(filter.len(), 1_000);
+        assert!(filter.layers.len() > 1);
+        assert!((0..1_000).all(|i| filter.contains(fingerprint(&i.to_string()))));
+
+        let false_positives = (1_000..101_000)
+            .filter(|i| filter.contains(fingerprint(&i.to_string())))
+            .count();
+        assert!(false_positives < 300);
+    }
+
+    #[test]
+    fn seen_filter() {
+        let mut seen = SeenFilter::new(2);
+
+        assert!(seen.insert(fingerprint("http://example.com/1")));
+        assert!(seen.insert(fingerprint("http://example.com/2")));
+        assert!(!seen.insert(fingerprint("http://example.com/1")));
+        assert_eq!(seen.hot.len(), 2);
+
+        // the hot set is moved into the Bloom filter once it's full
+        assert!(seen.insert(fingerprint("http://example.com/3")));
+        assert_eq!(seen.hot.len(), 1);
+        assert_eq!(seen.cold.len(), 2);
+        assert_eq!(seen.len(), 3);
+        assert!(seen.contains(fingerprint("http://example.com/1")));
+        assert!(seen.contains(fingerprint("http://example.com/2")));
+        assert!(seen.contains(fingerprint("http://example.com/3")));
+        assert!(!seen.contains(fingerprint("http://example.com/4")));
+    }
+
+    #[test]
+    fn seen_filter_write_and_read() {
+        let mut seen = SeenFilter::new(10);
+        for i in 0..25 {
+            seen.insert(fingerprint(&i.to_string()));
+        }
+        let mut state: Vec<u8> = vec![];
+        seen.write_to(&mut state).unwrap();
+
+        let mut restored = SeenFilter::new(10);
+        for line in state.lines() {
+            assert_eq!(restored.read_line(&line.unwrap()), Some(()));
+        }
+        assert_eq!(restored.len(), 25);
+        assert!((0..25).all(|i| restored.contains(fingerprint(&i.to_string()))));
+        assert!(!restored.insert(fingerprint("0")));
+        assert!(restored.insert(fingerprint("25")));
+
+        assert_eq!(restored.read_line("hot xyz"), None);
+        assert_eq!(restored.read_line("layer 10 64 7 1 00"), None);
+    }
+}
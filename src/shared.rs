@@ -1,29 +1,218 @@
-use crate::crawling::Crawling;
-use crate::error::CrawlerError;
+use crate::crawler::SkipReason;
+use crate::crawling::{Contact, Crawling};
+use crate::error::CrawlerError::{self, ParsingError, PersistingError};
+use crate::filter::EXCLUDED_EXTENSIONS;
+use crate::history::Revision;
 use crate::job::Job;
-use crate::job::BLACKLIST_CONTENT_TYPES;
+use crate::response::{send, Response, SizeLimits};
 use crate::traits::{Fetch, Persist};
 use chrono::{DateTime, Utc};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, USER_AGENT as USER_AGENT_HEADER};
+use reqwest::{Client, Proxy, RedirectPolicy, Url};
 use std::collections::hash_map::DefaultHasher;
+use std::collections::BTreeMap;
 use std::fs;
 use std::fs::File;
 use std::hash::{Hash, Hasher};
 use std::io::Write;
-use std::path::PathBuf;
-use std::time::SystemTime;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
 
-#[derive(Default, Clone, Eq, PartialEq, Hash)]
-pub struct MainFetcher;
+static USER_AGENT: &str = concat!("crawler/", env!("CARGO_PKG_VERSION"));
+static CONNECT_TIMEOUT: u64 = 10;
+static READ_TIMEOUT: u64 = 30;
+static TOTAL_TIMEOUT: u64 = 120;
+
+// the settings the client of a `MainFetcher` was built with
+#[derive(PartialEq, Eq, Hash, Debug, Clone)]
+pub struct ClientConfig {
+    pub user_agent: String,
+    pub connect_timeout: Option<Duration>,
+    // the maximum time to wait for a single read or write
+    pub read_timeout: Option<Duration>,
+    // the maximum time for a whole request including the content
+    pub total_timeout: Option<Duration>,
+    pub headers: Vec<(String, String)>,
+    // used for `http` and `https` URLs
+    pub proxy: Option<String>,
+    // NOTE: only meant for e.g. staging servers with self-signed certificates
+    pub accept_invalid_certs: bool,
+    pub size_limits: SizeLimits,
+}
+
+impl Default for ClientConfig {
+    fn default() -> Self {
+        ClientConfig {
+            user_agent: USER_AGENT.to_string(),
+            connect_timeout: Some(Duration::from_secs(CONNECT_TIMEOUT)),
+            read_timeout: Some(Duration::from_secs(READ_TIMEOUT)),
+            total_timeout: Some(Duration::from_secs(TOTAL_TIMEOUT)),
+            headers: vec![],
+            proxy: None,
+            accept_invalid_certs: false,
+            size_limits: SizeLimits::default(),
+        }
+    }
+}
+
+// two fetchers are equal if their clients were built with the same settings
+#[derive(Clone)]
+pub struct MainFetcher {
+    config: ClientConfig,
+    client: Client,
+}
 impl MainFetcher {
+    // NOTE: panics if the client can't be built, see `MainFetcherBuilder::build` otherwise
     pub fn new() -> Self {
-        MainFetcher
+        MainFetcher::builder().build().unwrap()
+    }
+
+    pub fn builder() -> MainFetcherBuilder {
+        MainFetcherBuilder {
+            config: ClientConfig::default(),
+        }
+    }
+
+    pub fn get_config(&self) -> &ClientConfig {
+        &self.config
+    }
+
+    fn send(&self, url: &Url, headers: HeaderMap) -> self::Result<Response> {
+        let blacklist = self.get_content_type_blacklist();
+        let config = &self.config;
+        send(
+            &self.client,
+            url,
+            headers,
+            blacklist,
+            config.total_timeout,
+            &config.size_limits,
+        )
+    }
+}
+impl Default for MainFetcher {
+    fn default() -> Self {
+        MainFetcher::new()
+    }
+}
+impl PartialEq for MainFetcher {
+    fn eq(&self, other: &Self) -> bool {
+        self.config == other.config
+    }
+}
+impl Eq for MainFetcher {}
+impl Hash for MainFetcher {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.config.hash(state);
     }
 }
 impl Fetch for MainFetcher {
     fn get_content_type_blacklist<'a>(&self) -> Option<Vec<&'a str>> {
-        let blacklist: Vec<&str> = From::from(&BLACKLIST_CONTENT_TYPES[..]);
+        let blacklist: Vec<&str> = From::from(&EXCLUDED_EXTENSIONS[..]);
         Some(blacklist)
     }
+
+    fn fetch_response(&self, url: &Url) -> self::Result<Response> {
+        self.send(url, HeaderMap::new())
+    }
+
+    fn fetch_if_changed(&self, url: &Url, revision: &Revision) -> self::Result<Response> {
+        self.send(url, revision.to_headers())
+    }
+}
+
+pub struct MainFetcherBuilder {
+    config: ClientConfig,
+}
+impl MainFetcherBuilder {
+    pub fn user_agent(mut self, user_agent: &str) -> Self {
+        self.config.user_agent = user_agent.to_string();
+        self
+    }
+
+    // `None` disables the timeout
+    pub fn connect_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.config.connect_timeout = timeout;
+        self
+    }
+
+    pub fn read_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.config.read_timeout = timeout;
+        self
+    }
+
+    pub fn total_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.config.total_timeout = timeout;
+        self
+    }
+
+    // sent with every request. Can be called several times
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.config
+            .headers
+            .push((name.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn proxy(mut self, proxy: &str) -> Self {
+        self.config.proxy = Some(proxy.to_string());
+        self
+    }
+
+    pub fn accept_invalid_certs(mut self, accept_invalid_certs: bool) -> Self {
+        self.config.accept_invalid_certs = accept_invalid_certs;
+        self
+    }
+
+    // `None` disables the limit for all content types without a limit of their own
+    pub fn max_size(mut self, max_size: Option<usize>) -> Self {
+        self.config.size_limits.max_size = max_size;
+        self
+    }
+
+    // the limit for content types which contain `content_type`, e.g. `html` or `application/pdf`.
+    // Can be called several times, the first matching one is used
+    pub fn max_size_for(mut self, content_type: &str, max_size: usize) -> Self {
+        self.config
+            .size_limits
+            .by_content_type
+            .push((content_type.to_string(), max_size));
+        self
+    }
+
+    // keeps the start of responses which exceed the limit instead of failing
+    pub fn truncate(mut self, truncate: bool) -> Self {
+        self.config.size_limits.truncate = truncate;
+        self
+    }
+
+    pub fn build(self) -> self::Result<MainFetcher> {
+        let config = self.config;
+        let mut headers = HeaderMap::new();
+        let invalid_header = |name: &str| ParsingError(format!("Invalid header \"{}\"", name));
+        headers.insert(
+            USER_AGENT_HEADER,
+            HeaderValue::from_str(&config.user_agent).map_err(|_| invalid_header("User-Agent"))?,
+        );
+        for (name, value) in config.headers.iter() {
+            let header_name =
+                HeaderName::from_bytes(name.as_bytes()).map_err(|_| invalid_header(name))?;
+            let header_value = HeaderValue::from_str(value).map_err(|_| invalid_header(name))?;
+            headers.insert(header_name, header_value);
+        }
+        // redirects are followed by the crawler so that every hop is checked
+        let mut builder = Client::builder()
+            .redirect(RedirectPolicy::none())
+            .default_headers(headers)
+            .connect_timeout(config.connect_timeout)
+            .timeout(config.read_timeout)
+            .danger_accept_invalid_certs(config.accept_invalid_certs);
+        if let Some(proxy) = &config.proxy {
+            builder = builder.proxy(Proxy::all(proxy.as_str())?);
+        }
+        let client = builder.build()?;
+        Ok(MainFetcher { config, client })
+    }
 }
 
 pub struct FSPersister {
@@ -37,6 +226,23 @@ impl FSPersister {
         Ok(FSPersister { out_dir })
     }
 
+    // continues to persist into the directory of a previous crawl
+    pub fn open(out_dir: &Path) -> self::Result<Self> {
+        if !out_dir.is_dir() {
+            return Err(PersistingError(format!(
+                "Directory \"{}\" doesn't exist",
+                out_dir.display()
+            )));
+        }
+        Ok(FSPersister {
+            out_dir: out_dir.to_path_buf(),
+        })
+    }
+
+    pub fn get_out_dir(&self) -> &Path {
+        &self.out_dir
+    }
+
     fn create_out_dir(root_dir: &str) -> self::Result<PathBuf> {
         create_ts_directory(root_dir)
     }
@@ -78,6 +284,66 @@ where
 {
     pub crawling: Crawling<A>,
     pub jobs: Option<Vec<Job<B>>>,
+    pub contacts: Option<Vec<Contact>>,
+    // the number of found links which weren't turned into jobs
+    pub skipped: BTreeMap<SkipReason, usize>,
+    // `None` if the page can't be fetched conditionally next time
+    pub revision: Option<Revision>,
 }
 
 pub type Result<T> = std::result::Result<T, CrawlerError>;
+
+#[cfg(test)]
+mod tests {
+    use crate::response::SizeLimits;
+    use crate::shared::{ClientConfig, MainFetcher};
+    use std::time::Duration;
+
+    #[test]
+    fn main_fetcher_builder() {
+        let fetcher = MainFetcher::builder()
+            .user_agent("MyBot/1.0")
+            .connect_timeout(Some(Duration::from_secs(5)))
+            .total_timeout(None)
+            .header("Accept-Language", "en")
+            .proxy("http://localhost:8080")
+            .max_size(None)
+            .max_size_for("html", 5_000_000)
+            .truncate(true)
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            fetcher.get_config(),
+            &ClientConfig {
+                user_agent: "MyBot/1.0".to_string(),
+                connect_timeout: Some(Duration::from_secs(5)),
+                total_timeout: None,
+                headers: vec![("Accept-Language".to_string(), "en".to_string())],
+                proxy: Some("http://localhost:8080".to_string()),
+                size_limits: SizeLimits {
+                    max_size: None,
+                    by_content_type: vec![("html".to_string(), 5_000_000)],
+                    truncate: true,
+                },
+                ..ClientConfig::default()
+            }
+        );
+        assert!(fetcher != MainFetcher::new());
+        assert!(fetcher.clone() == fetcher);
+    }
+
+    #[test]
+    fn main_fetcher_builder_invalid() {
+        let invalid_header = MainFetcher::builder()
+            .header("Accept Language", "en")
+            .build();
+        assert!(invalid_header
+            .err()
+            .unwrap()
+            .to_string()
+            .contains("Accept Language"));
+        assert!(MainFetcher::builder().user_agent("Bot\n").build().is_err());
+        assert!(MainFetcher::builder().proxy("not a proxy").build().is_err());
+    }
+}
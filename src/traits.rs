@@ -1,52 +1,101 @@
-use crate::error::CrawlerError::{ContentTypeError, RequestError};
+use crate::crawler::CrawlReport;
+use crate::crawling::Directives;
+use crate::error::CrawlerError;
+use crate::history::Revision;
+use crate::response::{send_following, Response, SizeLimits, CLIENT, MAX_REDIRECTS};
 use crate::shared;
-use reqwest::header::CONTENT_TYPE;
-use reqwest::Client;
-
-lazy_static! {
-    static ref CLIENT: Client = Client::new();
-}
+use reqwest::Url;
+use std::time::{Duration, Instant};
 
 pub trait Persist {
     fn persist(&self, id: &str, url: &str, content: &[u8]) -> shared::Result<usize>;
+
+    // persists the content under the URL it was fetched from. Override this to store e.g. the
+    // headers along with the content
+    fn persist_response(&self, id: &str, response: &Response) -> shared::Result<usize> {
+        self.persist(id, response.url.as_str(), &response.content)
+    }
+
+    // pages which ask not to be indexed via `<meta name="robots">` are skipped by default
+    fn should_persist(&self, _url: &str, directives: &Directives) -> bool {
+        !directives.noindex
+    }
 }
 
 pub trait Fetch {
+    // NOTE: only the content type and the content are returned. The status, the headers and the
+    // redirects are only available through `fetch_response`
     fn fetch(&self, url: &str) -> shared::Result<(String, Vec<u8>)> {
-        let mut resp = CLIENT.get(url).send()?;
-        if !resp.status().is_success() {
-            return Err(RequestError(format!("{}", resp.status())));
-        }
-
-        if let Some(header) = resp.headers().get(CONTENT_TYPE) {
-            let content_type = header.to_str().unwrap().to_string();
-
-            if self.get_content_type_blacklist().is_some()
-                && self
-                    .get_content_type_blacklist()
-                    .unwrap()
-                    .into_iter()
-                    .any(|t| content_type.contains(t))
-            {
-                return Err(ContentTypeError(format!(
-                    "Blacklisted Content-Type \"{}\" for URL \"{}\"",
-                    content_type, &url,
-                )));
-            }
-
-            let mut buffer: Vec<u8> = vec![];
-            resp.copy_to(&mut buffer)?;
-
-            return Ok((content_type, buffer));
-        }
-
-        Err(ContentTypeError(format!(
-            "Invalid Content-Type for URL \"{}\"",
-            &url,
-        )))
+        let url = Url::parse(url)?;
+        let blacklist = self.get_content_type_blacklist();
+        let size_limits = SizeLimits::default();
+        let response = send_following(&CLIENT, &url, blacklist, MAX_REDIRECTS, &size_limits)?;
+        Ok((response.content_type, response.content))
+    }
+
+    // the crawler only calls this method. By default it turns the result of `fetch` into a
+    // successful response so that fetchers which only implement `fetch` keep working. Redirects
+    // should be returned without following them so that the crawler can check every hop. Those
+    // which were followed anyway have to be listed in `Response::redirects`. Unsuccessful
+    // responses can be returned as they are, e.g. to honor their `Retry-After`
+    fn fetch_response(&self, url: &Url) -> shared::Result<Response> {
+        let started = Instant::now();
+        let (content_type, content) = self.fetch(url.as_str())?;
+        let mut response = Response::new(url.clone(), &content_type, content);
+        response.duration = started.elapsed();
+        Ok(response)
+    }
+
+    // asks for the content only if it changed since the page was fetched in a previous crawl.
+    // Unchanged pages are returned as a `304` without content. By default the content is always
+    // fetched again
+    fn fetch_if_changed(&self, url: &Url, _revision: &Revision) -> shared::Result<Response> {
+        self.fetch_response(url)
     }
 
     fn get_content_type_blacklist<'a>(&self) -> Option<Vec<&'a str>> {
         None
     }
 }
+
+// decides the order in which the frontier is crawled. Values with a higher score are crawled first
+// and values with the same score in the order they were found
+pub trait Score<T> {
+    fn score(&mut self, value: &T) -> i64;
+
+    // the lines a checkpoint keeps for scores which depend on what was scored before (e.g. a
+    // counter) so that a resumed crawl continues in the same order. Stateless scores have none
+    fn get_state(&self) -> Vec<String> {
+        vec![]
+    }
+
+    // restores a line of `get_state`. Stateless scores ignore it
+    fn restore_state(&mut self, _line: &str) -> Option<()> {
+        Some(())
+    }
+}
+
+// gets notified about the progress of a crawl. All callbacks do nothing by default. Apart from
+// `on_enqueue`, `should_stop` and `on_finish` they're called from the worker threads
+pub trait Observe: Send + Sync {
+    // only called for URLs which weren't enqueued before
+    fn on_enqueue(&self, _url: &Url, _depth: usize) {}
+
+    fn on_fetch_start(&self, _url: &Url) {}
+
+    // `status` is `None` if the fetcher doesn't report it
+    fn on_fetch_done(&self, _url: &Url, _status: Option<u16>, _bytes: usize, _duration: Duration) {}
+
+    fn on_persist(&self, _url: &Url, _bytes: usize) {}
+
+    // called for pages which couldn't be fetched or persisted
+    fn on_error(&self, _url: &Url, _err: &CrawlerError) {}
+
+    // called after every crawled page. Returning `true` stops the crawl once the in-flight jobs
+    // are done. The contacts of the report are only filled in at the end
+    fn should_stop(&self, _report: &CrawlReport) -> bool {
+        false
+    }
+
+    fn on_finish(&self, _report: &CrawlReport) {}
+}
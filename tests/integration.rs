@@ -1,10 +1,23 @@
 extern crate crawler;
 
-use crawler::crawler::Crawler;
+use crawler::crawler::{CrawlReport, Crawler, SkipReason, StopReason};
+use crawler::crawling::{Contact, LinkKind, SchemePolicy};
+use crawler::error::CrawlerError::{self, PersistingError, RequestError};
+use crawler::filter::UrlFilter;
+use crawler::history::Revision;
+use crawler::priority::Priority;
+use crawler::response::Response;
+use crawler::retry::RetryPolicy;
+use crawler::scope::Scope;
 use crawler::shared;
-use crawler::traits::{Fetch, Persist};
+use crawler::traits::{Fetch, Observe, Persist};
+use reqwest::Url;
 use std::collections::HashMap;
-use std::sync::Mutex;
+use std::env;
+use std::fs;
+use std::process;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 #[derive(Clone, Eq, PartialEq, Hash)]
 struct MockFetcher;
@@ -111,3 +124,1199 @@ fn integration() {
         ]
     )
 }
+
+#[test]
+fn integration_max_depth() {
+    let url = "http://example.com";
+    let num_threads: usize = 2;
+
+    let persister = MockPersister::new();
+    let fetcher = MockFetcher::new();
+
+    let mut crawler = Crawler::new(persister, fetcher, num_threads);
+    crawler.set_max_depth(Some(0));
+    let _result = crawler.start(url);
+
+    let persister_ref = crawler.get_persister();
+    let persister_hashmap = persister_ref.dest.lock().unwrap();
+
+    // only the starting URL has a depth of `0`
+    assert_eq!(persister_hashmap.len(), 1);
+    assert!(persister_hashmap.contains_key("http://example.com/"));
+}
+
+#[test]
+fn integration_queue_buffer() {
+    let url = "http://example.com";
+    let num_threads: usize = 2;
+
+    let persister = MockPersister::new();
+    let fetcher = MockFetcher::new();
+
+    let mut crawler = Crawler::new(persister, fetcher, num_threads);
+    // all but one of the found URLs have to be spilled to disk
+    crawler.set_queue_buffer(1);
+    let report = crawler.start(url).unwrap();
+
+    let persister_ref = crawler.get_persister();
+    let persister_hashmap = persister_ref.dest.lock().unwrap();
+
+    assert_eq!(report.fetched, 6);
+    assert_eq!(persister_hashmap.len(), 6);
+}
+
+#[test]
+fn integration_max_pages() {
+    let url = "http://example.com";
+    let num_threads: usize = 2;
+
+    let persister = MockPersister::new();
+    let fetcher = MockFetcher::new();
+
+    let mut crawler = Crawler::new(persister, fetcher, num_threads);
+    crawler.set_max_pages(Some(3));
+    let report = crawler.start(url).unwrap();
+
+    let persister_ref = crawler.get_persister();
+    let persister_hashmap = persister_ref.dest.lock().unwrap();
+
+    assert_eq!(report.stop_reason, StopReason::MaxPages);
+    assert_eq!(report.fetched, 3);
+    assert_eq!(persister_hashmap.len(), 3);
+}
+
+#[test]
+fn integration_resume() {
+    let url = "http://example.com";
+    let num_threads: usize = 2;
+    let state = std::env::temp_dir().join(format!("crawler-resume-{}", std::process::id()));
+    let state = state.to_str().unwrap();
+
+    let mut crawler = Crawler::new(MockPersister::new(), MockFetcher::new(), num_threads);
+    crawler.set_max_pages(Some(3));
+    crawler.set_checkpoint(state, Duration::from_secs(60));
+    let report = crawler.start(url).unwrap();
+    assert_eq!(report.stop_reason, StopReason::MaxPages);
+    let first_persister = crawler.get_persister();
+    let first = first_persister.dest.lock().unwrap();
+
+    let mut crawler = Crawler::new(MockPersister::new(), MockFetcher::new(), num_threads);
+    crawler.set_checkpoint(state, Duration::from_secs(60));
+    let report = crawler.resume(state).unwrap();
+    let second_persister = crawler.get_persister();
+    let second = second_persister.dest.lock().unwrap();
+
+    // the resumed crawl only fetches the pages which weren't crawled before
+    assert_eq!(report.stop_reason, StopReason::Finished);
+    assert_eq!(report.fetched, 6);
+    assert_eq!(first.len(), 3);
+    assert_eq!(second.len(), 3);
+    assert!(second.keys().all(|url| !first.contains_key(url)));
+    std::fs::remove_file(state).unwrap();
+}
+
+#[test]
+fn integration_resume_max_pages() {
+    // another host than `integration_retries` so that the attempts don't mix
+    let url = "http://resumed.example.com";
+    let num_threads: usize = 1;
+    let state = env::temp_dir().join(format!("crawler-resume-max-pages-{}", process::id()));
+    let state = state.to_str().unwrap();
+    let retry_policy = RetryPolicy {
+        max_retries: 3,
+        base_delay: Duration::from_millis(1),
+        max_delay: Duration::from_millis(10),
+        max_retry_after: Duration::from_secs(1),
+    };
+
+    let mut crawler = Crawler::new(MockPersister::new(), RetryFetcher, num_threads);
+    crawler.set_max_pages(Some(2));
+    crawler.set_retry_policy(retry_policy.clone());
+    crawler.set_checkpoint(state, Duration::from_secs(60));
+    let report = crawler.start(url).unwrap();
+    assert_eq!(report.stop_reason, StopReason::MaxPages);
+    assert_eq!(report.fetched, 2);
+
+    let mut crawler = Crawler::new(MockPersister::new(), RetryFetcher, num_threads);
+    crawler.set_max_pages(Some(3));
+    crawler.set_retry_policy(retry_policy);
+    crawler.set_checkpoint(state, Duration::from_secs(60));
+    let report = crawler.resume(state).unwrap();
+    let persister = crawler.get_persister();
+
+    // the failed and retried pages before the checkpoint don't count towards the limit after it
+    assert_eq!(report.fetched, 3);
+    assert_eq!(persister.dest.lock().unwrap().len(), 1);
+    fs::remove_file(state).unwrap();
+}
+
+#[test]
+fn integration_priority() {
+    let url = "http://example.com";
+    let num_threads: usize = 1;
+
+    let persister = MockPersister::new();
+    let fetcher = MockFetcher::new();
+
+    let mut crawler = Crawler::new(persister, fetcher, num_threads);
+    crawler.set_max_pages(Some(3));
+    crawler
+        .set_scorer(Priority::Keywords(vec!["imprint".to_string(), "jane".to_string()]).to_score());
+    crawler.start(url).unwrap();
+
+    let persister_ref = crawler.get_persister();
+    let persister_hashmap = persister_ref.dest.lock().unwrap();
+
+    // the pages with a keyword in their URL are crawled right after the starting URL
+    assert_eq!(persister_hashmap.len(), 3);
+    assert!(persister_hashmap.contains_key("https://example.com/imprint"));
+    assert!(persister_hashmap.contains_key("http://jane.doe.com/about"));
+}
+
+#[test]
+fn integration_finished() {
+    let url = "http://example.com";
+    let num_threads: usize = 2;
+
+    let persister = MockPersister::new();
+    let fetcher = MockFetcher::new();
+
+    let mut crawler = Crawler::new(persister, fetcher, num_threads);
+    crawler.set_max_pages(Some(6));
+    let report = crawler.start(url).unwrap();
+
+    assert_eq!(report.stop_reason, StopReason::Finished);
+    assert_eq!(report.fetched, 6);
+    assert!(report.bytes > 0);
+}
+
+#[test]
+fn integration_scope() {
+    let url = "http://example.com";
+    let num_threads: usize = 2;
+
+    let persister = MockPersister::new();
+    let fetcher = MockFetcher::new();
+
+    let mut crawler = Crawler::new(persister, fetcher, num_threads);
+    crawler.set_scope(Scope::SameHost);
+    let _result = crawler.start(url);
+
+    let persister_ref = crawler.get_persister();
+    let persister_hashmap = persister_ref.dest.lock().unwrap();
+
+    let mut keys: Vec<String> = persister_hashmap.keys().cloned().collect();
+    keys.sort();
+
+    assert_eq!(
+        keys,
+        vec![
+            "http://example.com/",
+            "https://example.com/about",
+            "https://example.com/imprint"
+        ]
+    );
+}
+
+#[derive(Clone, Eq, PartialEq, Hash)]
+struct RobotsFetcher;
+impl Fetch for RobotsFetcher {
+    fn fetch(&self, url: &str) -> shared::Result<(String, Vec<u8>)> {
+        if url.ends_with("/robots.txt") {
+            let content = b"User-agent: *\nDisallow: /about\n".to_vec();
+            return Ok(("text/plain".to_string(), content));
+        }
+        MockFetcher::new().fetch(url)
+    }
+}
+
+#[test]
+fn integration_url_filter() {
+    let url = "http://example.com";
+    let num_threads: usize = 2;
+
+    let persister = MockPersister::new();
+    let fetcher = MockFetcher::new();
+
+    let mut crawler = Crawler::new(persister, fetcher, num_threads);
+    crawler.set_url_filter(UrlFilter::new(vec![
+        "exclude host:doe.com".parse().unwrap(),
+        "exclude glob:*/imprint".parse().unwrap(),
+    ]));
+    crawler.start(url).unwrap();
+
+    let persister_ref = crawler.get_persister();
+    let persister_hashmap = persister_ref.dest.lock().unwrap();
+
+    let mut keys: Vec<&String> = persister_hashmap.keys().collect();
+    keys.sort();
+    assert_eq!(
+        keys,
+        vec!["http://example.com/", "https://example.com/about"]
+    );
+}
+
+#[test]
+fn integration_robots() {
+    let url = "http://example.com";
+    let num_threads: usize = 2;
+
+    let persister = MockPersister::new();
+    let fetcher = RobotsFetcher;
+
+    let mut crawler = Crawler::new(persister, fetcher, num_threads);
+    let _result = crawler.start(url);
+
+    let persister_ref = crawler.get_persister();
+    let persister_hashmap = persister_ref.dest.lock().unwrap();
+
+    let mut keys: Vec<String> = persister_hashmap.keys().cloned().collect();
+    keys.sort();
+
+    // all `/about` pages are disallowed
+    assert_eq!(
+        keys,
+        vec![
+            "http://example.com/",
+            "https://example.com/imprint",
+            "https://www.doe.com/"
+        ]
+    );
+}
+
+#[derive(Clone, Eq, PartialEq, Hash)]
+struct UnavailableRobotsFetcher;
+impl Fetch for UnavailableRobotsFetcher {
+    fn fetch(&self, url: &str) -> shared::Result<(String, Vec<u8>)> {
+        if url.ends_with("/robots.txt") {
+            return Err(RequestError("503 Service Unavailable".to_string()));
+        }
+        MockFetcher::new().fetch(url)
+    }
+}
+
+#[test]
+fn integration_robots_seed() {
+    let num_threads: usize = 2;
+
+    // the starting URL is disallowed as well
+    let mut crawler = Crawler::new(MockPersister::new(), RobotsFetcher, num_threads);
+    let report = crawler.start("http://example.com/about").unwrap();
+    assert_eq!(report.persisted, 0);
+    assert_eq!(report.skipped[&SkipReason::Robots], 1);
+
+    // a `robots.txt` which can't be fetched because of a server error disallows everything
+    let mut crawler = Crawler::new(MockPersister::new(), UnavailableRobotsFetcher, num_threads);
+    let report = crawler.start("http://example.com").unwrap();
+    assert_eq!(report.persisted, 0);
+    assert_eq!(report.skipped[&SkipReason::Robots], 1);
+}
+
+#[test]
+fn integration_ignore_robots() {
+    let url = "http://example.com";
+    let num_threads: usize = 2;
+
+    let persister = MockPersister::new();
+    let fetcher = RobotsFetcher;
+
+    let mut crawler = Crawler::new(persister, fetcher, num_threads);
+    crawler.set_respect_robots(false);
+    let _result = crawler.start(url);
+
+    let persister_ref = crawler.get_persister();
+    let persister_hashmap = persister_ref.dest.lock().unwrap();
+
+    assert_eq!(persister_hashmap.len(), 6);
+}
+
+#[test]
+fn integration_politeness() {
+    let url = "http://example.com";
+    let num_threads: usize = 4;
+
+    let persister = MockPersister::new();
+    let fetcher = MockFetcher::new();
+
+    let mut crawler = Crawler::new(persister, fetcher, num_threads);
+    crawler.set_delay(Duration::from_millis(50));
+    crawler.set_max_per_host(Some(1));
+    let report = crawler.start(url).unwrap();
+
+    // there are 3 URLs on `example.com` so we have to wait twice
+    assert_eq!(report.fetched, 6);
+    assert!(report.elapsed >= Duration::from_millis(100));
+}
+
+#[derive(Clone, Eq, PartialEq, Hash)]
+struct PriorityFetcher;
+impl Fetch for PriorityFetcher {
+    fn fetch(&self, url: &str) -> shared::Result<(String, Vec<u8>)> {
+        let content_type = "text/html".to_string();
+        let content = match url {
+            "http://example.com/" => {
+                br#"<a href="/a">A</a><a href="/b">B</a><a href="/c">C</a>"#.to_vec()
+            }
+            "http://example.com/a" => br#"<a href="/important">Important</a>"#.to_vec(),
+            _ => vec![],
+        };
+        Ok((content_type, content))
+    }
+}
+
+#[test]
+fn integration_politeness_priority() {
+    let url = "http://example.com";
+    let num_threads: usize = 1;
+
+    let persister = MockPersister::new();
+    let fetcher = PriorityFetcher;
+
+    let mut crawler = Crawler::new(persister, fetcher, num_threads);
+    crawler.set_delay(Duration::from_millis(20));
+    crawler.set_max_pages(Some(3));
+    crawler.set_scorer(Priority::Keywords(vec!["important".to_string()]).to_score());
+    crawler.start(url).unwrap();
+
+    let persister_ref = crawler.get_persister();
+    let persister_hashmap = persister_ref.dest.lock().unwrap();
+
+    // the page which is found while waiting for the host is still crawled before the others
+    assert_eq!(persister_hashmap.len(), 3);
+    assert!(persister_hashmap.contains_key("http://example.com/a"));
+    assert!(persister_hashmap.contains_key("http://example.com/important"));
+}
+
+#[derive(Clone, Eq, PartialEq, Hash)]
+struct DirectivesFetcher;
+impl Fetch for DirectivesFetcher {
+    fn fetch(&self, url: &str) -> shared::Result<(String, Vec<u8>)> {
+        let content_type = "text/html".to_string();
+        let content = match url {
+            "http://example.com/" => br###"
+                <html>
+                    <head><meta name="robots" content="noindex"></head>
+                    <body>
+                        <a href="/follow">Follow</a>
+                        <a href="/nofollow" rel="nofollow">No Follow</a>
+                    </body>
+                </html>
+            "###
+            .to_vec(),
+            _ => br###"
+                <html>
+                    <head><meta name="robots" content="nofollow"></head>
+                    <body><a href="/never">Never</a></body>
+                </html>
+            "###
+            .to_vec(),
+        };
+        Ok((content_type, content))
+    }
+}
+
+#[test]
+fn integration_directives() {
+    let url = "http://example.com";
+    let num_threads: usize = 2;
+
+    let persister = MockPersister::new();
+    let fetcher = DirectivesFetcher;
+
+    let mut crawler = Crawler::new(persister, fetcher, num_threads);
+    let report = crawler.start(url).unwrap();
+
+    let persister_ref = crawler.get_persister();
+    let persister_hashmap = persister_ref.dest.lock().unwrap();
+
+    let keys: Vec<String> = persister_hashmap.keys().cloned().collect();
+
+    // the starting URL is `noindex` and `/follow` is `nofollow`
+    assert_eq!(report.fetched, 2);
+    assert_eq!(report.persisted, 1);
+    assert_eq!(report.skipped[&SkipReason::Noindex], 1);
+    assert_eq!(report.skipped[&SkipReason::Nofollow], 2);
+    assert_eq!(keys, vec!["http://example.com/follow"]);
+}
+
+#[test]
+fn integration_ignore_nofollow() {
+    let url = "http://example.com";
+    let num_threads: usize = 2;
+
+    // `nofollow` is still honored without `robots.txt`
+    let mut crawler = Crawler::new(MockPersister::new(), DirectivesFetcher, num_threads);
+    crawler.set_respect_robots(false);
+    let report = crawler.start(url).unwrap();
+    assert_eq!(report.fetched, 2);
+    assert_eq!(report.skipped[&SkipReason::Nofollow], 2);
+
+    let mut crawler = Crawler::new(MockPersister::new(), DirectivesFetcher, num_threads);
+    crawler.set_respect_nofollow(false);
+    let report = crawler.start(url).unwrap();
+    assert_eq!(report.fetched, 4);
+    assert!(!report.skipped.contains_key(&SkipReason::Nofollow));
+}
+
+#[derive(Clone, Eq, PartialEq, Hash)]
+struct LinkKindsFetcher;
+impl Fetch for LinkKindsFetcher {
+    fn fetch(&self, _url: &str) -> shared::Result<(String, Vec<u8>)> {
+        let content_type = "text/html".to_string();
+        let content = br###"
+            <html>
+                <body>
+                    <a href="/anchor">Anchor</a>
+                    <iframe src="/iframe"></iframe>
+                </body>
+            </html>
+        "###
+        .to_vec();
+        Ok((content_type, content))
+    }
+}
+
+#[test]
+fn integration_link_kinds() {
+    let url = "http://example.com";
+    let num_threads: usize = 2;
+
+    let persister = MockPersister::new();
+    let fetcher = LinkKindsFetcher;
+
+    let mut crawler = Crawler::new(persister, fetcher, num_threads);
+    crawler.set_link_kinds(vec![LinkKind::Iframe]);
+    let _result = crawler.start(url);
+
+    let persister_ref = crawler.get_persister();
+    let persister_hashmap = persister_ref.dest.lock().unwrap();
+
+    let mut keys: Vec<String> = persister_hashmap.keys().cloned().collect();
+    keys.sort();
+
+    assert_eq!(
+        keys,
+        vec!["http://example.com/", "http://example.com/iframe"]
+    );
+}
+
+#[derive(Clone, Eq, PartialEq, Hash)]
+struct DuplicatesFetcher;
+impl Fetch for DuplicatesFetcher {
+    fn fetch(&self, _url: &str) -> shared::Result<(String, Vec<u8>)> {
+        let content_type = "text/html".to_string();
+        let content = br###"
+            <html>
+                <body>
+                    <a href="/a#x">A</a>
+                    <a href="http://EXAMPLE.com:80/a">A</a>
+                    <a href="/a?utm_source=newsletter">A</a>
+                    <a href="/b?b=1&a=2">B</a>
+                    <a href="/b?a=2&b=1">B</a>
+                </body>
+            </html>
+        "###
+        .to_vec();
+        Ok((content_type, content))
+    }
+}
+
+#[test]
+fn integration_canonicalization() {
+    let url = "http://example.com/#top";
+    let num_threads: usize = 2;
+
+    let persister = MockPersister::new();
+    let fetcher = DuplicatesFetcher;
+
+    let mut crawler = Crawler::new(persister, fetcher, num_threads);
+    let _result = crawler.start(url);
+
+    let persister_ref = crawler.get_persister();
+    let persister_hashmap = persister_ref.dest.lock().unwrap();
+
+    let mut keys: Vec<String> = persister_hashmap.keys().cloned().collect();
+    keys.sort();
+
+    assert_eq!(
+        keys,
+        vec![
+            "http://example.com/",
+            "http://example.com/a",
+            "http://example.com/b?a=2&b=1"
+        ]
+    );
+}
+
+#[derive(Clone, Eq, PartialEq, Hash)]
+struct ContactsFetcher;
+impl Fetch for ContactsFetcher {
+    fn fetch(&self, _url: &str) -> shared::Result<(String, Vec<u8>)> {
+        let content_type = "text/html".to_string();
+        let content = br###"
+            <html>
+                <body>
+                    <a href="/about">About</a>
+                    <a href="mailto:jdoe@example.com">Mail</a>
+                    <a href="tel:+1-555-0100">Call</a>
+                    <a href="javascript:void(0)">Click</a>
+                </body>
+            </html>
+        "###
+        .to_vec();
+        Ok((content_type, content))
+    }
+}
+
+#[test]
+fn integration_contacts() {
+    let url = "http://example.com";
+    let num_threads: usize = 2;
+
+    let persister = MockPersister::new();
+    let fetcher = ContactsFetcher;
+
+    let mut crawler = Crawler::new(persister, fetcher, num_threads);
+    crawler.set_scheme_policy(SchemePolicy {
+        collect_contacts: true,
+        ..SchemePolicy::default()
+    });
+    let report = crawler.start(url).unwrap();
+
+    assert_eq!(report.fetched, 2);
+    // 3 rejected links on each of the 2 pages
+    assert_eq!(report.skipped[&SkipReason::Scheme], 6);
+    assert_eq!(
+        report.contacts,
+        vec![
+            Contact::Email("jdoe@example.com".to_string()),
+            Contact::Phone("+1-555-0100".to_string())
+        ]
+    );
+}
+
+#[derive(Clone, Eq, PartialEq, Hash)]
+struct ReportFetcher;
+impl Fetch for ReportFetcher {
+    fn fetch(&self, url: &str) -> shared::Result<(String, Vec<u8>)> {
+        let content_type = "text/html".to_string();
+        match url {
+            "http://example.com/" => Ok((
+                content_type,
+                br###"
+                    <html>
+                        <body>
+                            <a href="/found">Found</a>
+                            <a href="/missing">Missing</a>
+                            <a href="/broken">Broken</a>
+                            <a href="http://jdoe.com/">John Doe</a>
+                        </body>
+                    </html>
+                "###
+                .to_vec(),
+            )),
+            "http://example.com/found" | "http://example.com/broken" => {
+                Ok((content_type, b"Hello World!".to_vec()))
+            }
+            _ => Err(RequestError("404 Not Found".to_string())),
+        }
+    }
+}
+
+struct ReportPersister;
+impl Persist for ReportPersister {
+    fn persist(&self, _id: &str, url: &str, content: &[u8]) -> shared::Result<usize> {
+        if url.ends_with("/broken") {
+            return Err(PersistingError("Disk full".to_string()));
+        }
+        Ok(content.len())
+    }
+}
+
+#[test]
+fn integration_report() {
+    let url = "http://example.com";
+    let num_threads: usize = 2;
+
+    let mut crawler = Crawler::new(ReportPersister, ReportFetcher, num_threads);
+    crawler.set_scope(Scope::SameHost);
+    let report = crawler.start(url).unwrap();
+
+    // `/broken` was fetched but couldn't be persisted
+    assert_eq!(report.stop_reason, StopReason::Finished);
+    assert_eq!(report.fetched, 3);
+    assert_eq!(report.persisted, 2);
+    assert_eq!(report.count_skipped(), 1);
+    assert_eq!(report.skipped[&SkipReason::Scope], 1);
+    assert_eq!(report.count_failed(), 2);
+    assert_eq!(report.failed["RequestError"], 1);
+    assert_eq!(report.failed["PersistingError"], 1);
+
+    let mut failed_urls: Vec<&str> = report.failed_urls.iter().map(|url| url.as_str()).collect();
+    failed_urls.sort();
+    assert_eq!(
+        failed_urls,
+        vec!["http://example.com/broken", "http://example.com/missing"]
+    );
+    assert!(report
+        .to_string()
+        .contains("Failed 2 (PersistingError 1, RequestError 1)"));
+}
+
+struct PanickingPersister;
+impl Persist for PanickingPersister {
+    fn persist(&self, _id: &str, url: &str, content: &[u8]) -> shared::Result<usize> {
+        if url == "https://example.com/imprint" {
+            panic!("Disk gone");
+        }
+        Ok(content.len())
+    }
+}
+
+#[test]
+fn integration_worker_panic() {
+    let url = "http://example.com";
+    let num_threads: usize = 2;
+
+    let mut crawler = Crawler::new(PanickingPersister, MockFetcher::new(), num_threads);
+    let err = crawler.start(url).unwrap_err();
+
+    // the crawl ends instead of waiting for the job of the dead worker
+    assert_eq!(err.get_kind(), "WorkerError");
+    assert!(err.to_string().contains("Disk gone"));
+}
+
+#[derive(Default)]
+struct MockObserver {
+    events: Mutex<Vec<String>>,
+    // stops the crawl once this many pages were fetched
+    max_fetched: Option<usize>,
+}
+impl MockObserver {
+    fn count(&self, event: &str) -> usize {
+        let events = self.events.lock().unwrap();
+        events
+            .iter()
+            .filter(|e| e.split(' ').next() == Some(event))
+            .count()
+    }
+}
+impl Observe for MockObserver {
+    fn on_enqueue(&self, url: &Url, depth: usize) {
+        let event = format!("enqueue {} {}", url, depth);
+        self.events.lock().unwrap().push(event);
+    }
+
+    fn on_fetch_start(&self, url: &Url) {
+        self.events
+            .lock()
+            .unwrap()
+            .push(format!("fetch_start {}", url));
+    }
+
+    fn on_fetch_done(&self, url: &Url, _status: Option<u16>, bytes: usize, _duration: Duration) {
+        let event = format!("fetch_done {} {}", url, bytes);
+        self.events.lock().unwrap().push(event);
+    }
+
+    fn on_persist(&self, url: &Url, _bytes: usize) {
+        self.events.lock().unwrap().push(format!("persist {}", url));
+    }
+
+    fn on_error(&self, url: &Url, err: &CrawlerError) {
+        let event = format!("error {} {}", url, err.get_kind());
+        self.events.lock().unwrap().push(event);
+    }
+
+    fn should_stop(&self, report: &CrawlReport) -> bool {
+        self.max_fetched
+            .is_some_and(|max_fetched| report.fetched >= max_fetched)
+    }
+
+    fn on_finish(&self, report: &CrawlReport) {
+        let event = format!("finish {}", report.fetched);
+        self.events.lock().unwrap().push(event);
+    }
+}
+
+#[test]
+fn integration_observer() {
+    let url = "http://example.com";
+    let num_threads: usize = 2;
+
+    let observer = Arc::new(MockObserver::default());
+    let mut crawler = Crawler::new(ReportPersister, ReportFetcher, num_threads);
+    crawler.set_scope(Scope::SameHost);
+    crawler.set_observer(observer.clone());
+    crawler.start(url).unwrap();
+
+    // `robots.txt` is fetched once and missing
+    assert_eq!(observer.count("enqueue"), 4);
+    assert_eq!(observer.count("fetch_start"), 5);
+    assert_eq!(observer.count("fetch_done"), 3);
+    assert_eq!(observer.count("persist"), 2);
+    assert_eq!(observer.count("error"), 2);
+
+    let events = observer.events.lock().unwrap();
+    assert_eq!(events.first().unwrap(), "enqueue http://example.com/ 0");
+    assert!(events.contains(&"fetch_start http://example.com/robots.txt".to_string()));
+    assert!(events.contains(&"error http://example.com/missing RequestError".to_string()));
+    assert!(events.contains(&"error http://example.com/broken PersistingError".to_string()));
+    assert_eq!(events.last().unwrap(), "finish 3");
+}
+
+#[test]
+fn integration_observer_stop() {
+    let url = "http://example.com";
+    let num_threads: usize = 1;
+
+    let observer = Arc::new(MockObserver {
+        max_fetched: Some(2),
+        ..MockObserver::default()
+    });
+    let mut crawler = Crawler::new(MockPersister::new(), MockFetcher::new(), num_threads);
+    crawler.set_observer(observer.clone());
+    let report = crawler.start(url).unwrap();
+
+    assert_eq!(report.stop_reason, StopReason::Stopped);
+    assert_eq!(report.fetched, 2);
+    // the `robots.txt` of every site which is linked to is fetched as well
+    let events = observer.events.lock().unwrap();
+    let pages = events
+        .iter()
+        .filter(|event| event.starts_with("fetch_start") && !event.ends_with("/robots.txt"))
+        .count();
+    assert_eq!(pages, 2);
+}
+
+#[derive(Clone, Eq, PartialEq, Hash)]
+struct ResponseFetcher;
+impl Fetch for ResponseFetcher {
+    fn fetch_response(&self, url: &Url) -> shared::Result<Response> {
+        if url.path() == "/robots.txt" {
+            return Err(RequestError("404 Not Found".to_string()));
+        }
+        // the starting URL redirects into a directory which holds a relative link
+        let (final_url, content) = match url.as_str() {
+            "http://example.com/" => (
+                "http://example.com/home/",
+                &b"<a href=\"about\">About</a>"[..],
+            ),
+            _ => (url.as_str(), &b"Hello World!"[..]),
+        };
+        let mut response = Response::new(
+            Url::parse(final_url).unwrap(),
+            "text/html",
+            content.to_vec(),
+        );
+        if response.url != *url {
+            response.status = 203;
+            response.redirects = vec![url.clone()];
+        }
+        response
+            .headers
+            .insert("x-crawled-by", "crawler".parse().unwrap());
+        Ok(response)
+    }
+}
+
+struct ResponsePersister {
+    dest: Mutex<HashMap<String, String>>,
+}
+impl Persist for ResponsePersister {
+    fn persist(&self, _id: &str, _url: &str, _content: &[u8]) -> shared::Result<usize> {
+        unreachable!()
+    }
+
+    fn persist_response(&self, _id: &str, response: &Response) -> shared::Result<usize> {
+        let mut value = format!(
+            "{} {} {}",
+            response.status,
+            response.get_requested_url(),
+            response.headers["x-crawled-by"].to_str().unwrap()
+        );
+        if response.truncated {
+            value.push_str(" (truncated)");
+        }
+        let mut dest = self.dest.lock().unwrap();
+        dest.insert(response.url.to_string(), value);
+        Ok(response.content.len())
+    }
+}
+
+#[test]
+fn integration_response() {
+    let url = "http://example.com";
+    let num_threads: usize = 2;
+
+    let persister = ResponsePersister {
+        dest: Mutex::new(HashMap::new()),
+    };
+    let mut crawler = Crawler::new(persister, ResponseFetcher, num_threads);
+    let report = crawler.start(url).unwrap();
+
+    let persister_ref = crawler.get_persister();
+    let persister_hashmap = persister_ref.dest.lock().unwrap();
+
+    assert_eq!(report.fetched, 2);
+    assert_eq!(
+        persister_hashmap["http://example.com/home/"],
+        "203 http://example.com/ crawler"
+    );
+    assert_eq!(
+        persister_hashmap["http://example.com/home/about"],
+        "200 http://example.com/home/about crawler"
+    );
+}
+
+#[derive(Clone, Eq, PartialEq, Hash)]
+struct RedirectsFetcher;
+impl Fetch for RedirectsFetcher {
+    fn fetch_response(&self, url: &Url) -> shared::Result<Response> {
+        let location = match url.path() {
+            "/robots.txt" => return Err(RequestError("404 Not Found".to_string())),
+            "/" | "/old" => Some("/home/".to_string()),
+            "/new" => Some("/target".to_string()),
+            "/away" => Some("http://jdoe.com/".to_string()),
+            // redirects forever
+            path if path.starts_with("/loop") => Some(format!("{}/next", path)),
+            _ => None,
+        };
+        let content = br###"
+            <a href="/old">Old</a>
+            <a href="/new">New</a>
+            <a href="/target">Target</a>
+            <a href="/away">Away</a>
+            <a href="/loop">Loop</a>
+        "###;
+        let mut response = Response::new(url.clone(), "text/html", content.to_vec());
+        if let Some(location) = location {
+            response.status = 301;
+            response.content.clear();
+            response
+                .headers
+                .insert("location", location.parse().unwrap());
+        }
+        Ok(response)
+    }
+}
+
+#[test]
+fn integration_redirects() {
+    let url = "http://example.com";
+    let num_threads: usize = 2;
+
+    let persister = MockPersister::new();
+    let mut crawler = Crawler::new(persister, RedirectsFetcher, num_threads);
+    crawler.set_scope(Scope::SameHost);
+    crawler.set_max_redirects(2);
+    let report = crawler.start(url).unwrap();
+
+    let persister_ref = crawler.get_persister();
+    let persister_hashmap = persister_ref.dest.lock().unwrap();
+
+    let mut keys: Vec<String> = persister_hashmap.keys().cloned().collect();
+    keys.sort();
+
+    // pages are stored under the URL they were redirected to and only once
+    assert_eq!(
+        keys,
+        vec!["http://example.com/home/", "http://example.com/target"]
+    );
+    assert_eq!(report.fetched, 2);
+    // `/old` and `/new` lead to pages which were already seen
+    assert_eq!(report.skipped[&SkipReason::Duplicate], 2);
+    assert_eq!(report.skipped[&SkipReason::Scope], 1);
+    assert_eq!(report.failed["RequestError"], 1);
+    assert_eq!(report.failed_urls[0].as_str(), "http://example.com/loop");
+}
+
+// the URLs which were requested so far, to fail differently depending on the attempt
+static ATTEMPTS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+#[derive(Clone, Eq, PartialEq, Hash)]
+struct RetryFetcher;
+impl Fetch for RetryFetcher {
+    fn fetch_response(&self, url: &Url) -> shared::Result<Response> {
+        if url.path() == "/robots.txt" {
+            return Err(RequestError("404 Not Found".to_string()));
+        }
+        let attempt = {
+            let mut attempts = ATTEMPTS.lock().unwrap();
+            attempts.push(url.to_string());
+            attempts
+                .iter()
+                .filter(|attempt| *attempt == url.as_str())
+                .count()
+        };
+        let content = match url.path() {
+            "/" => &b"<a href=\"flaky\"></a><a href=\"busy\"></a><a href=\"missing\"></a>"[..],
+            _ => &b"Hello World!"[..],
+        };
+        let mut response = Response::new(url.clone(), "text/html", content.to_vec());
+        match url.path() {
+            "/flaky" if attempt <= 2 => {
+                return Err(RequestError("503 Service Unavailable".to_string()))
+            }
+            "/busy" if attempt == 1 => {
+                response.status = 429;
+                response.headers.insert("retry-after", "0".parse().unwrap());
+            }
+            "/missing" => response.status = 404,
+            _ => (),
+        }
+        Ok(response)
+    }
+}
+
+#[test]
+fn integration_retries() {
+    let url = "http://example.com";
+    let num_threads: usize = 2;
+
+    let persister = MockPersister::new();
+    let mut crawler = Crawler::new(persister, RetryFetcher, num_threads);
+    crawler.set_retry_policy(RetryPolicy {
+        max_retries: 3,
+        base_delay: Duration::from_millis(1),
+        max_delay: Duration::from_millis(10),
+        max_retry_after: Duration::from_secs(1),
+    });
+    let report = crawler.start(url).unwrap();
+
+    assert_eq!(report.fetched, 3);
+    assert_eq!(report.persisted, 3);
+    assert_eq!(report.retries, 3);
+    assert_eq!(report.failed["RequestError"], 1);
+    assert_eq!(
+        report.failed_urls,
+        vec![Url::parse("http://example.com/missing").unwrap()]
+    );
+    let attempts = ATTEMPTS.lock().unwrap();
+    let attempts = attempts
+        .iter()
+        .filter(|attempt| attempt.starts_with("http://example.com/"));
+    assert_eq!(attempts.count(), 7);
+}
+
+#[test]
+fn integration_retries_max_pages() {
+    // another host than `integration_retries` so that the attempts don't mix
+    let url = "http://limited.example.com";
+    let num_threads: usize = 1;
+
+    let persister = MockPersister::new();
+    let mut crawler = Crawler::new(persister, RetryFetcher, num_threads);
+    crawler.set_max_pages(Some(2));
+    crawler.set_retry_policy(RetryPolicy {
+        max_retries: 3,
+        base_delay: Duration::from_millis(1),
+        max_delay: Duration::from_millis(10),
+        max_retry_after: Duration::from_secs(1),
+    });
+    let report = crawler.start(url).unwrap();
+
+    // neither the retries nor the failed page count towards the limit
+    assert_eq!(report.stop_reason, StopReason::MaxPages);
+    assert_eq!(report.fetched, 2);
+    assert!(report.retries >= 1);
+}
+
+#[derive(Clone, Eq, PartialEq, Hash)]
+struct TruncatingFetcher;
+impl Fetch for TruncatingFetcher {
+    fn fetch_response(&self, url: &Url) -> shared::Result<Response> {
+        if url.path() == "/robots.txt" {
+            return Err(RequestError("404 Not Found".to_string()));
+        }
+        let content = match url.path() {
+            "/" => &b"<a href=\"large\">Large</a>"[..],
+            _ => &b"Hello Wor"[..],
+        };
+        let mut response = Response::new(url.clone(), "text/html", content.to_vec());
+        response.truncated = url.path() == "/large";
+        response
+            .headers
+            .insert("x-crawled-by", "crawler".parse().unwrap());
+        Ok(response)
+    }
+}
+
+#[test]
+fn integration_truncated() {
+    let url = "http://example.com";
+    let num_threads: usize = 2;
+
+    let persister = ResponsePersister {
+        dest: Mutex::new(HashMap::new()),
+    };
+    let mut crawler = Crawler::new(persister, TruncatingFetcher, num_threads);
+    let report = crawler.start(url).unwrap();
+
+    let persister_ref = crawler.get_persister();
+    let persister_hashmap = persister_ref.dest.lock().unwrap();
+
+    assert_eq!(report.fetched, 2);
+    assert_eq!(report.persisted, 2);
+    assert_eq!(report.truncated, 1);
+    assert_eq!(
+        persister_hashmap["http://example.com/"],
+        "200 http://example.com/ crawler"
+    );
+    assert_eq!(
+        persister_hashmap["http://example.com/large"],
+        "200 http://example.com/large crawler (truncated)"
+    );
+}
+
+// every page has an `ETag` which is its path followed by its version
+#[derive(Clone, Eq, PartialEq, Hash)]
+struct RevisionFetcher {
+    // the path of the page which changed since the last crawl
+    changed: Option<&'static str>,
+    // the path of the page which doesn't exist anymore
+    gone: Option<&'static str>,
+}
+impl RevisionFetcher {
+    fn get_etag(&self, url: &Url) -> String {
+        let version = if self.changed == Some(url.path()) {
+            2
+        } else {
+            1
+        };
+        format!("\"{}-{}\"", url.path(), version)
+    }
+}
+impl Fetch for RevisionFetcher {
+    fn fetch_response(&self, url: &Url) -> shared::Result<Response> {
+        if url.path() == "/robots.txt" || self.gone == Some(url.path()) {
+            return Err(RequestError("404 Not Found".to_string()));
+        }
+        let content = match url.path() {
+            "/" => &b"<a href=\"a\">A</a><a href=\"b\">B</a>"[..],
+            _ => &b"Hello World!"[..],
+        };
+        let mut response = Response::new(url.clone(), "text/html", content.to_vec());
+        let etag = self.get_etag(url);
+        response.headers.insert("etag", etag.parse().unwrap());
+        Ok(response)
+    }
+
+    fn fetch_if_changed(&self, url: &Url, revision: &Revision) -> shared::Result<Response> {
+        if revision.etag == Some(self.get_etag(url)) && self.gone != Some(url.path()) {
+            let mut response = Response::new(url.clone(), "text/html", vec![]);
+            response.status = 304;
+            return Ok(response);
+        }
+        self.fetch_response(url)
+    }
+}
+
+#[test]
+fn integration_history() {
+    let url = "http://example.com";
+    let num_threads: usize = 2;
+    let path = env::temp_dir().join(format!("crawler-integration-history-{}", process::id()));
+    let history = path.to_str().unwrap();
+
+    let fetcher = RevisionFetcher {
+        changed: None,
+        gone: None,
+    };
+    let mut crawler = Crawler::new(MockPersister::new(), fetcher, num_threads);
+    crawler.set_history(history);
+    let report = crawler.start(url).unwrap();
+    assert_eq!(report.fetched, 3);
+    assert_eq!(report.persisted, 3);
+    assert_eq!(report.changed + report.unchanged, 0);
+
+    // only the changed page is fetched and persisted again
+    let fetcher = RevisionFetcher {
+        changed: Some("/b"),
+        gone: None,
+    };
+    let mut crawler = Crawler::new(MockPersister::new(), fetcher, num_threads);
+    crawler.set_history(history);
+    let report = crawler.start(url).unwrap();
+    let persister_ref = crawler.get_persister();
+    let persister_hashmap = persister_ref.dest.lock().unwrap();
+    assert_eq!(report.fetched, 1);
+    assert_eq!(report.persisted, 1);
+    assert_eq!(report.changed, 1);
+    assert_eq!(report.unchanged, 2);
+    assert!(persister_hashmap.contains_key("http://example.com/b"));
+
+    // the changed page was remembered with its new `ETag`
+    let fetcher = RevisionFetcher {
+        changed: Some("/b"),
+        gone: None,
+    };
+    let mut crawler = Crawler::new(MockPersister::new(), fetcher, num_threads);
+    crawler.set_history(history);
+    let report = crawler.start(url).unwrap();
+    assert_eq!(report.fetched, 0);
+    assert_eq!(report.unchanged, 3);
+
+    // a page which is gone is forgotten, so it's fetched without a condition once it's back
+    let fetcher = RevisionFetcher {
+        changed: None,
+        gone: Some("/a"),
+    };
+    let mut crawler = Crawler::new(MockPersister::new(), fetcher, num_threads);
+    crawler.set_history(history);
+    let report = crawler.start(url).unwrap();
+    assert_eq!(report.failed["RequestError"], 1);
+    assert_eq!(report.unchanged, 1);
+    let fetcher = RevisionFetcher {
+        changed: None,
+        gone: None,
+    };
+    let mut crawler = Crawler::new(MockPersister::new(), fetcher, num_threads);
+    crawler.set_history(history);
+    let report = crawler.start(url).unwrap();
+    assert_eq!(report.fetched, 1);
+    assert_eq!(report.changed, 0);
+    assert_eq!(report.unchanged, 2);
+    fs::remove_file(&path).unwrap();
+}
+
+// the URLs which were requested by `UnavailableFetcher`
+static UNAVAILABLE_ATTEMPTS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+#[derive(Clone, Eq, PartialEq, Hash)]
+struct UnavailableFetcher;
+impl Fetch for UnavailableFetcher {
+    fn fetch_response(&self, url: &Url) -> shared::Result<Response> {
+        if url.path() == "/robots.txt" {
+            return Err(RequestError("404 Not Found".to_string()));
+        }
+        if url.path() == "/" {
+            let content = b"<a href=\"a\"></a><a href=\"b\"></a><a href=\"c\"></a>".to_vec();
+            return Ok(Response::new(url.clone(), "text/html", content));
+        }
+        UNAVAILABLE_ATTEMPTS.lock().unwrap().push(url.to_string());
+        Err(RequestError("503 Service Unavailable".to_string()))
+    }
+}
+
+#[test]
+fn integration_retries_spilled() {
+    let url = "http://example.com";
+    let num_threads: usize = 1;
+
+    let mut crawler = Crawler::new(MockPersister::new(), UnavailableFetcher, num_threads);
+    // retried jobs go through the spill file
+    crawler.set_queue_buffer(1);
+    crawler.set_retry_policy(RetryPolicy {
+        max_retries: 2,
+        base_delay: Duration::from_millis(1),
+        max_delay: Duration::from_millis(5),
+        max_retry_after: Duration::from_secs(1),
+    });
+    let report = crawler.start(url).unwrap();
+
+    assert_eq!(report.fetched, 1);
+    assert_eq!(report.retries, 6);
+    assert_eq!(report.failed["RequestError"], 3);
+    assert_eq!(UNAVAILABLE_ATTEMPTS.lock().unwrap().len(), 9);
+}